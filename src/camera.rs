@@ -0,0 +1,303 @@
+use nalgebra_glm as glm;
+
+const ZOOM_FACTOR: f32 = 1.1_f32;
+const FIT_MARGIN: f32 = 0.9_f32;
+const MAX_ZOOM: f32 = 64.0_f32;
+const MIN_ZOOM: f32 = 0.01_f32;
+
+// Maps between canvas coordinates (origin at the center, y up) and screen
+// coordinates (window pixels, origin at the top left, y down). The canvas
+// point at `center` is shown at the middle of the viewport, scaled by
+// `zoom` and rotated counterclockwise by `rotation` radians.
+#[derive(Clone, Copy)]
+pub struct Camera2D {
+    center: glm::Vec2,
+    zoom: f32,
+    rotation: f32,
+    viewport_pos: glm::Vec2,
+    viewport_size: glm::Vec2,
+}
+
+impl Camera2D {
+
+    pub fn new(viewport_width: f32, viewport_height: f32) -> Self {
+        Self {
+            center: glm::vec2(0.0, 0.0),
+            zoom: 1.0,
+            rotation: 0.0,
+            viewport_pos: glm::vec2(0.0, 0.0),
+            viewport_size: glm::vec2(viewport_width, viewport_height),
+        }
+    }
+
+    pub fn get_zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    pub fn get_rotation(&self) -> f32 {
+        self.rotation
+    }
+
+    pub fn set_rotation(&mut self, rotation: f32) {
+        self.rotation = rotation.rem_euclid(std::f32::consts::TAU);
+    }
+
+    // The same view shown in another part of the window.
+    pub fn with_viewport(&self, pos: &glm::Vec2, size: &glm::Vec2) -> Self {
+        Self {
+            viewport_pos: *pos,
+            viewport_size: *size,
+            ..*self
+        }
+    }
+
+    pub fn get_viewport_pos(&self) -> glm::Vec2 {
+        self.viewport_pos
+    }
+
+    pub fn get_viewport_size(&self) -> glm::Vec2 {
+        self.viewport_size
+    }
+
+    pub fn set_viewport_size(&mut self, viewport_size: &glm::Vec2) {
+        self.viewport_size = *viewport_size;
+    }
+
+    pub fn screen_to_canvas(&self, screen_pos: &glm::Vec2) -> glm::Vec2 {
+        let view_pos = glm::vec2(
+            screen_pos.x - self.viewport_pos.x - self.viewport_size.x / 2.0,
+            self.viewport_pos.y + self.viewport_size.y / 2.0 - screen_pos.y,
+        );
+        glm::rotate_vec2(&view_pos, -self.rotation) / self.zoom + self.center
+    }
+
+    pub fn canvas_to_screen(&self, canvas_pos: &glm::Vec2) -> glm::Vec2 {
+        let view_pos = glm::rotate_vec2(&(canvas_pos - self.center), self.rotation) * self.zoom;
+        glm::vec2(
+            self.viewport_pos.x + self.viewport_size.x / 2.0 + view_pos.x,
+            self.viewport_pos.y + self.viewport_size.y / 2.0 - view_pos.y,
+        )
+    }
+
+    // Transform for a quad spanning -1..1 that shows a canvas of
+    // `canvas_size` pixels, into normalized device coordinates. Pixels are
+    // centered on integer canvas coordinates, where the algorithms put them.
+    pub fn compute_transform(&self, canvas_size: &glm::Vec2) -> glm::Mat3 {
+
+        let identity = glm::Mat3::identity();
+
+        let to_canvas = glm::translate2d(&identity, &glm::vec2(-0.5, -0.5))
+            * glm::scale2d(&identity, &(canvas_size * 0.5));
+        let translation = glm::translate2d(&identity, &-self.center);
+        let scaling = glm::scale2d(&identity, &glm::vec2(self.zoom, self.zoom));
+        let rotation = glm::rotate2d(&identity, self.rotation);
+        let to_ndc = glm::scale2d(&identity, &glm::vec2(
+            2.0 / self.viewport_size.x, 2.0 / self.viewport_size.y
+        ));
+
+        to_ndc * rotation * scaling * translation * to_canvas
+    }
+
+    // The canvas point under `anchor`, in screen coordinates, stays in place.
+    pub fn zoom_at(&mut self, scroll_amount: f64, anchor: &glm::Vec2) {
+
+        if scroll_amount == 0.0 {
+            return;
+        }
+
+        let anchor_canvas_pos = self.screen_to_canvas(anchor);
+        self.set_zoom(self.zoom * ZOOM_FACTOR.powf(scroll_amount as f32));
+        self.center += anchor_canvas_pos - self.screen_to_canvas(anchor);
+    }
+
+    // Moves the canvas along with a screen space delta.
+    pub fn pan(&mut self, screen_delta: &glm::Vec2) {
+        let view_delta = glm::vec2(screen_delta.x, -screen_delta.y);
+        self.center -= glm::rotate_vec2(&view_delta, -self.rotation) / self.zoom;
+    }
+
+    // Centers the canvas rectangle and zooms so it fills the viewport.
+    pub fn fit(&mut self, min: &glm::Vec2, max: &glm::Vec2) {
+
+        let corners = [
+            glm::vec2(min.x, min.y),
+            glm::vec2(max.x, min.y),
+            glm::vec2(min.x, max.y),
+            glm::vec2(max.x, max.y),
+        ];
+
+        self.center = (min + max) * 0.5;
+
+        let mut extent = glm::vec2(0.0_f32, 0.0);
+        for corner in &corners {
+            let view_pos = glm::rotate_vec2(&(corner - self.center), self.rotation);
+            extent = glm::max2(&extent, &view_pos.abs());
+        }
+
+        let zoom = (self.viewport_size.x / (2.0 * extent.x))
+            .min(self.viewport_size.y / (2.0 * extent.y));
+        self.set_zoom(zoom * FIT_MARGIN);
+    }
+
+    // Shows the whole canvas unrotated, magnified by a whole factor when it
+    // is smaller than the viewport so all pixels get the same size.
+    pub fn reset(&mut self, canvas_size: &glm::Vec2) {
+
+        let zoom = (self.viewport_size.x / canvas_size.x)
+            .min(self.viewport_size.y / canvas_size.y);
+
+        self.center = glm::vec2(0.0, 0.0);
+        self.rotation = 0.0;
+        self.set_zoom(if zoom >= 1.0 { zoom.floor() } else { zoom });
+    }
+
+    fn set_zoom(&mut self, zoom: f32) {
+        self.zoom = zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    const EPSILON: f32 = 1e-3;
+
+    fn assert_close(a: &glm::Vec2, b: &glm::Vec2) {
+        assert!(glm::distance(a, b) < EPSILON, "{:?} != {:?}", a, b);
+    }
+
+    fn cameras() -> Vec<Camera2D> {
+
+        let mut cameras = Vec::new();
+
+        for &(zoom_steps, rotation, pan) in &[
+            (0.0, 0.0, glm::vec2(0.0, 0.0)),
+            (5.0, 0.0, glm::vec2(30.0, -12.0)),
+            (-7.0, 1.0, glm::vec2(-100.0, 45.5)),
+            (20.0, 4.2, glm::vec2(3.25, 7.75)),
+        ] {
+            let mut camera = Camera2D::new(800.0, 600.0);
+            camera.set_rotation(rotation);
+            camera.zoom_at(zoom_steps, &glm::vec2(123.0, 456.0));
+            camera.pan(&pan);
+            cameras.push(camera);
+            cameras.push(camera.with_viewport(&glm::vec2(400.0, 300.0), &glm::vec2(400.0, 300.0)));
+        }
+
+        cameras
+    }
+
+    #[test]
+    fn default_camera_maps_window_center_to_origin() {
+
+        let camera = Camera2D::new(800.0, 600.0);
+
+        assert_close(&camera.screen_to_canvas(&glm::vec2(400.0, 300.0)), &glm::vec2(0.0, 0.0));
+        assert_close(&camera.screen_to_canvas(&glm::vec2(0.0, 0.0)), &glm::vec2(-400.0, 300.0));
+        assert_close(&camera.canvas_to_screen(&glm::vec2(400.0, -300.0)), &glm::vec2(800.0, 600.0));
+    }
+
+    #[test]
+    fn screen_canvas_round_trip() {
+
+        for camera in cameras() {
+            for screen_pos in [glm::vec2(0.0, 0.0), glm::vec2(400.0, 300.0), glm::vec2(799.0, 17.5)] {
+                let canvas_pos = camera.screen_to_canvas(&screen_pos);
+                assert_close(&camera.canvas_to_screen(&canvas_pos), &screen_pos);
+            }
+            for canvas_pos in [glm::vec2(0.0, 0.0), glm::vec2(-250.0, 120.0)] {
+                let screen_pos = camera.canvas_to_screen(&canvas_pos);
+                assert_close(&camera.screen_to_canvas(&screen_pos), &canvas_pos);
+            }
+        }
+    }
+
+    #[test]
+    fn shader_transform_matches_canvas_to_screen() {
+
+        let canvas_size = glm::vec2(800.0, 600.0);
+
+        for camera in cameras() {
+
+            let transform = camera.compute_transform(&canvas_size);
+
+            for quad_pos in [glm::vec2(-1.0, -1.0), glm::vec2(1.0, 0.5), glm::vec2(0.25, 1.0)] {
+
+                // The viewport transform GL applies after the shader.
+                let ndc = transform * glm::vec3(quad_pos.x, quad_pos.y, 1.0);
+                let viewport_pos = camera.get_viewport_pos();
+                let viewport_size = camera.get_viewport_size();
+                let screen_pos = glm::vec2(
+                    viewport_pos.x + (ndc.x + 1.0) * viewport_size.x / 2.0,
+                    viewport_pos.y + (1.0 - ndc.y) * viewport_size.y / 2.0,
+                );
+
+                let canvas_pos = quad_pos.component_mul(&(canvas_size * 0.5))
+                    - glm::vec2(0.5, 0.5);
+                assert_close(&camera.canvas_to_screen(&canvas_pos), &screen_pos);
+            }
+        }
+    }
+
+    #[test]
+    fn reset_magnifies_small_canvases_by_whole_factors() {
+
+        let mut camera = Camera2D::new(800.0, 600.0);
+
+        camera.reset(&glm::vec2(800.0, 600.0));
+        assert_eq!(camera.get_zoom(), 1.0);
+
+        camera.reset(&glm::vec2(64.0, 48.0));
+        assert_eq!(camera.get_zoom(), 12.0);
+        assert_close(&camera.canvas_to_screen(&glm::vec2(0.0, 0.0)), &glm::vec2(400.0, 300.0));
+    }
+
+    #[test]
+    fn zoom_keeps_anchor_in_place() {
+
+        let mut camera = Camera2D::new(800.0, 600.0);
+        camera.set_rotation(0.5);
+        let anchor = glm::vec2(620.0, 80.0);
+        let anchor_canvas_pos = camera.screen_to_canvas(&anchor);
+
+        camera.zoom_at(3.0, &anchor);
+        assert_close(&camera.screen_to_canvas(&anchor), &anchor_canvas_pos);
+
+        camera.zoom_at(-10.0, &anchor);
+        assert_close(&camera.screen_to_canvas(&anchor), &anchor_canvas_pos);
+    }
+
+    #[test]
+    fn pan_follows_cursor() {
+
+        for mut camera in cameras() {
+
+            let canvas_pos = camera.screen_to_canvas(&glm::vec2(200.0, 100.0));
+            camera.pan(&glm::vec2(15.0, -40.0));
+
+            assert_close(&camera.canvas_to_screen(&canvas_pos), &glm::vec2(215.0, 60.0));
+        }
+    }
+
+    #[test]
+    fn fit_shows_the_whole_rectangle() {
+
+        let min = glm::vec2(-10.0, 20.0);
+        let max = glm::vec2(90.0, 70.0);
+
+        for mut camera in cameras() {
+
+            camera.fit(&min, &max);
+
+            let viewport_min = camera.get_viewport_pos();
+            let viewport_max = viewport_min + camera.get_viewport_size();
+
+            for corner in [min, max, glm::vec2(min.x, max.y), glm::vec2(max.x, min.y)] {
+                let screen_pos = camera.canvas_to_screen(&corner);
+                assert!((viewport_min.x..=viewport_max.x).contains(&screen_pos.x));
+                assert!((viewport_min.y..=viewport_max.y).contains(&screen_pos.y));
+            }
+        }
+    }
+}
@@ -20,6 +20,7 @@ impl Default for Color {
     }
 }
 
+#[derive(PartialEq)]
 pub struct ColorU8 {
     pub r: u8,
     pub g: u8,
@@ -38,6 +39,55 @@ impl Default for ColorU8 {
     }
 }
 
+impl ColorU8 {
+
+    // Interpolates every channel, alpha included, straight in sRGB.
+    pub fn lerp(&self, other: &ColorU8, t: f32) -> ColorU8 {
+
+        let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+
+        ColorU8 {
+            r: mix(self.r, other.r),
+            g: mix(self.g, other.g),
+            b: mix(self.b, other.b),
+            a: mix(self.a, other.a),
+        }
+    }
+
+    // Interpolates the color channels in linear light, alpha is already
+    // linear.
+    pub fn lerp_linear(&self, other: &ColorU8, t: f32) -> ColorU8 {
+
+        let mix = |a: u8, b: u8| {
+            let (a, b) = (srgb_to_linear(a as f32 / 255.0), srgb_to_linear(b as f32 / 255.0));
+            (linear_to_srgb(a + (b - a) * t) * 255.0).round() as u8
+        };
+
+        ColorU8 {
+            r: mix(self.r, other.r),
+            g: mix(self.g, other.g),
+            b: mix(self.b, other.b),
+            a: self.lerp(other, t).a,
+        }
+    }
+}
+
+pub fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+pub fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
 impl Clone for ColorU8 {
     fn clone(&self) -> Self {
         Self {
@@ -0,0 +1,339 @@
+use crate::common::ColorU8;
+
+// The Porter-Duff operators, by how much of the source and of the
+// destination they keep.
+#[derive(PartialEq, Clone, Copy)]
+pub enum CompositeOp {
+    Clear,
+    Src,
+    Dst,
+    SrcOver,
+    DstOver,
+    SrcIn,
+    DstIn,
+    SrcOut,
+    DstOut,
+    SrcAtop,
+    DstAtop,
+    Xor,
+}
+
+impl CompositeOp {
+
+    pub const ALL: [CompositeOp; 12] = [
+        CompositeOp::Clear,
+        CompositeOp::Src,
+        CompositeOp::Dst,
+        CompositeOp::SrcOver,
+        CompositeOp::DstOver,
+        CompositeOp::SrcIn,
+        CompositeOp::DstIn,
+        CompositeOp::SrcOut,
+        CompositeOp::DstOut,
+        CompositeOp::SrcAtop,
+        CompositeOp::DstAtop,
+        CompositeOp::Xor,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CompositeOp::Clear => "Clear",
+            CompositeOp::Src => "Source",
+            CompositeOp::Dst => "Destination",
+            CompositeOp::SrcOver => "Source over",
+            CompositeOp::DstOver => "Destination over",
+            CompositeOp::SrcIn => "Source in",
+            CompositeOp::DstIn => "Destination in",
+            CompositeOp::SrcOut => "Source out",
+            CompositeOp::DstOut => "Destination out",
+            CompositeOp::SrcAtop => "Source atop",
+            CompositeOp::DstAtop => "Destination atop",
+            CompositeOp::Xor => "Xor",
+        }
+    }
+
+    // The fractions of the source and of the destination that are kept,
+    // given their alphas.
+    fn factors(&self, src_alpha: f32, dst_alpha: f32) -> (f32, f32) {
+        match self {
+            CompositeOp::Clear => (0.0, 0.0),
+            CompositeOp::Src => (1.0, 0.0),
+            CompositeOp::Dst => (0.0, 1.0),
+            CompositeOp::SrcOver => (1.0, 1.0 - src_alpha),
+            CompositeOp::DstOver => (1.0 - dst_alpha, 1.0),
+            CompositeOp::SrcIn => (dst_alpha, 0.0),
+            CompositeOp::DstIn => (0.0, src_alpha),
+            CompositeOp::SrcOut => (1.0 - dst_alpha, 0.0),
+            CompositeOp::DstOut => (0.0, 1.0 - src_alpha),
+            CompositeOp::SrcAtop => (dst_alpha, 1.0 - src_alpha),
+            CompositeOp::DstAtop => (1.0 - dst_alpha, src_alpha),
+            CompositeOp::Xor => (1.0 - dst_alpha, 1.0 - src_alpha),
+        }
+    }
+}
+
+// How the source color mixes with the destination where both are there,
+// before the operator keeps its part of them.
+#[derive(PartialEq, Clone, Copy)]
+pub enum BlendMode {
+    Normal,
+    Multiply,
+    Screen,
+    Additive,
+    Darken,
+    Lighten,
+    Difference,
+}
+
+impl BlendMode {
+
+    pub const ALL: [BlendMode; 7] = [
+        BlendMode::Normal,
+        BlendMode::Multiply,
+        BlendMode::Screen,
+        BlendMode::Additive,
+        BlendMode::Darken,
+        BlendMode::Lighten,
+        BlendMode::Difference,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BlendMode::Normal => "Normal",
+            BlendMode::Multiply => "Multiply",
+            BlendMode::Screen => "Screen",
+            BlendMode::Additive => "Additive",
+            BlendMode::Darken => "Darken",
+            BlendMode::Lighten => "Lighten",
+            BlendMode::Difference => "Difference",
+        }
+    }
+
+    // Straight colors, from 0 to 1.
+    fn blend(&self, dst: f32, src: f32) -> f32 {
+        match self {
+            BlendMode::Normal => src,
+            BlendMode::Multiply => dst * src,
+            BlendMode::Screen => dst + src - dst * src,
+            BlendMode::Additive => (dst + src).min(1.0),
+            BlendMode::Darken => dst.min(src),
+            BlendMode::Lighten => dst.max(src),
+            BlendMode::Difference => (dst - src).abs(),
+        }
+    }
+}
+
+// Composites a straight alpha source over a premultiplied destination and
+// returns the premultiplied result.
+pub fn composite(src: &ColorU8, dst: &ColorU8, operator: CompositeOp, blend_mode: BlendMode) -> ColorU8 {
+
+    let src_alpha = src.a as f32 / 255.0;
+    let dst_alpha = dst.a as f32 / 255.0;
+    let (src_factor, dst_factor) = operator.factors(src_alpha, dst_alpha);
+
+    let channel = |src: u8, dst: u8| {
+
+        let src = src as f32 / 255.0;
+        let dst_premultiplied = dst as f32 / 255.0;
+        let dst = if dst_alpha > 0.0 { dst_premultiplied / dst_alpha } else { 0.0 };
+
+        // Where there is no destination the source keeps its own color.
+        let blended = (1.0 - dst_alpha) * src + dst_alpha * blend_mode.blend(dst, src);
+
+        to_u8(src_alpha * src_factor * blended + dst_premultiplied * dst_factor)
+    };
+
+    ColorU8 {
+        r: channel(src.r, dst.r),
+        g: channel(src.g, dst.g),
+        b: channel(src.b, dst.b),
+        a: to_u8(src_alpha * src_factor + dst_alpha * dst_factor),
+    }
+}
+
+// Raster operations work on the bits of the colors, like the drawing modes
+// of old display hardware. Alpha is taken as one more channel, so drawing
+// the same line twice in XOR gives back the background, transparent or not.
+#[derive(PartialEq, Clone, Copy)]
+pub enum RasterOp {
+    Copy,
+    Xor,
+    And,
+    Or,
+    // Draws the inverse of the line color.
+    Not,
+    // Inverts what is under the line, whatever its color.
+    Invert,
+}
+
+impl RasterOp {
+
+    pub const ALL: [RasterOp; 6] = [
+        RasterOp::Copy,
+        RasterOp::Xor,
+        RasterOp::And,
+        RasterOp::Or,
+        RasterOp::Not,
+        RasterOp::Invert,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            RasterOp::Copy => "Copy",
+            RasterOp::Xor => "XOR",
+            RasterOp::And => "AND",
+            RasterOp::Or => "OR",
+            RasterOp::Not => "NOT",
+            RasterOp::Invert => "Invert",
+        }
+    }
+
+    pub fn apply(&self, src: &ColorU8, dst: &ColorU8) -> ColorU8 {
+
+        let channel = |src: u8, dst: u8| match self {
+            RasterOp::Copy => src,
+            RasterOp::Xor => dst ^ src,
+            RasterOp::And => dst & src,
+            RasterOp::Or => dst | src,
+            RasterOp::Not => !src,
+            RasterOp::Invert => !dst,
+        };
+
+        // The colors drawn keep the line's alpha.
+        let alpha = match self {
+            RasterOp::Copy | RasterOp::Not => src.a,
+            _ => channel(src.a, dst.a),
+        };
+
+        ColorU8 {
+            r: channel(src.r, dst.r),
+            g: channel(src.g, dst.g),
+            b: channel(src.b, dst.b),
+            a: alpha,
+        }
+    }
+}
+
+pub fn unpremultiply(color: &ColorU8) -> ColorU8 {
+
+    if color.a == 0 {
+        return ColorU8 { r: 0, g: 0, b: 0, a: 0 };
+    }
+
+    let channel = |value: u8| ((value as u32 * 255 + color.a as u32 / 2) / color.a as u32).min(255) as u8;
+
+    ColorU8 {
+        r: channel(color.r),
+        g: channel(color.g),
+        b: channel(color.b),
+        a: color.a,
+    }
+}
+
+fn to_u8(value: f32) -> u8 {
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn color(r: u8, g: u8, b: u8, a: u8) -> ColorU8 {
+        ColorU8 { r, g, b, a }
+    }
+
+    fn assert_color_eq(actual: &ColorU8, expected: &ColorU8) {
+        assert_eq!(
+            (actual.r, actual.g, actual.b, actual.a),
+            (expected.r, expected.g, expected.b, expected.a),
+        );
+    }
+
+    #[test]
+    fn xor_twice_restores_the_background() {
+        let line = color(255, 200, 0, 255);
+        for background in [color(0, 0, 0, 0), color(40, 80, 120, 255), color(10, 20, 30, 128)] {
+            let once = RasterOp::Xor.apply(&line, &background);
+            let twice = RasterOp::Xor.apply(&line, &once);
+            assert_color_eq(&twice, &background);
+        }
+    }
+
+    #[test]
+    fn xor_on_a_transparent_background_draws_the_line() {
+        let line = color(255, 200, 0, 255);
+        let drawn = RasterOp::Xor.apply(&line, &color(0, 0, 0, 0));
+        assert_color_eq(&drawn, &line);
+    }
+
+    #[test]
+    fn opaque_source_over_is_the_source() {
+        let src = color(200, 100, 50, 255);
+        let result = composite(&src, &color(10, 20, 30, 255), CompositeOp::SrcOver, BlendMode::Normal);
+        assert_color_eq(&result, &src);
+    }
+
+    #[test]
+    fn clear_is_transparent() {
+        let (src, dst) = (color(200, 100, 50, 255), color(10, 20, 30, 255));
+        let result = composite(&src, &dst, CompositeOp::Clear, BlendMode::Normal);
+        assert_color_eq(&result, &color(0, 0, 0, 0));
+    }
+
+    #[test]
+    fn in_and_out_over_a_transparent_destination() {
+        let src = color(200, 100, 50, 255);
+        let transparent = color(0, 0, 0, 0);
+
+        // Nothing to be in.
+        let result = composite(&src, &transparent, CompositeOp::SrcIn, BlendMode::Normal);
+        assert_color_eq(&result, &transparent);
+
+        // Nothing to keep.
+        let result = composite(&src, &transparent, CompositeOp::DstOut, BlendMode::Normal);
+        assert_color_eq(&result, &transparent);
+
+        // All of the source is out of it.
+        let result = composite(&src, &transparent, CompositeOp::SrcOut, BlendMode::Normal);
+        assert_color_eq(&result, &src);
+    }
+
+    #[test]
+    fn xor_of_opaque_colors_is_transparent() {
+        let (src, dst) = (color(200, 100, 50, 255), color(10, 20, 30, 255));
+        let result = composite(&src, &dst, CompositeOp::Xor, BlendMode::Normal);
+        assert_color_eq(&result, &color(0, 0, 0, 0));
+    }
+
+    #[test]
+    fn multiply_and_screen() {
+        let src = color(255, 128, 0, 255);
+        let dst = color(128, 128, 128, 255);
+
+        let result = composite(&src, &dst, CompositeOp::SrcOver, BlendMode::Multiply);
+        assert_color_eq(&result, &color(128, 64, 0, 255));
+
+        let result = composite(&src, &dst, CompositeOp::SrcOver, BlendMode::Screen);
+        assert_color_eq(&result, &color(255, 192, 128, 255));
+    }
+
+    #[test]
+    fn unpremultiply_undoes_compositing_on_transparent() {
+        assert_color_eq(&unpremultiply(&color(64, 32, 0, 128)), &color(128, 64, 0, 128));
+        assert_color_eq(&unpremultiply(&color(9, 9, 9, 0)), &color(0, 0, 0, 0));
+
+        let transparent = color(0, 0, 0, 0);
+        for alpha in [128, 200, 255] {
+            for value in [0, 1, 100, 128, 254, 255] {
+                let src = color(value, 255 - value, value / 2, alpha);
+                let premultiplied = composite(&src, &transparent, CompositeOp::Src, BlendMode::Normal);
+                let result = unpremultiply(&premultiplied);
+                assert_eq!(result.a, alpha);
+                // Rounding to 8 bits twice can be off by one.
+                for (actual, expected) in [(result.r, src.r), (result.g, src.g), (result.b, src.b)] {
+                    assert!((actual as i32 - expected as i32).abs() <= 1);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use nalgebra_glm as glm;
+
+const MAX_SUBDIVISION_DEPTH: u32 = 16;
+const MAX_ARC_SEGMENTS: u32 = 1024;
+
+// Appends the points of a cubic bezier after `p0`, subdividing until the
+// control points are within `tolerance` of the chord.
+pub fn flatten_cubic(
+    p0: glm::Vec2,
+    p1: glm::Vec2,
+    p2: glm::Vec2,
+    p3: glm::Vec2,
+    tolerance: f32,
+    points: &mut Vec<glm::Vec2>,
+) {
+    flatten_cubic_rec(p0, p1, p2, p3, tolerance, 0, points);
+}
+
+fn flatten_cubic_rec(
+    p0: glm::Vec2,
+    p1: glm::Vec2,
+    p2: glm::Vec2,
+    p3: glm::Vec2,
+    tolerance: f32,
+    depth: u32,
+    points: &mut Vec<glm::Vec2>,
+) {
+
+    let flatness = distance_to_line(&p1, &p0, &p3).max(distance_to_line(&p2, &p0, &p3));
+
+    if flatness <= tolerance || depth >= MAX_SUBDIVISION_DEPTH {
+        points.push(p3);
+        return;
+    }
+
+    let p01 = (p0 + p1) * 0.5;
+    let p12 = (p1 + p2) * 0.5;
+    let p23 = (p2 + p3) * 0.5;
+    let p012 = (p01 + p12) * 0.5;
+    let p123 = (p12 + p23) * 0.5;
+    let mid = (p012 + p123) * 0.5;
+
+    flatten_cubic_rec(p0, p01, p012, mid, tolerance, depth + 1, points);
+    flatten_cubic_rec(mid, p123, p23, p3, tolerance, depth + 1, points);
+}
+
+// Number of chords needed so that an arc of the given radius and sweep
+// (radians) deviates at most `tolerance` from the true arc.
+pub fn arc_segment_count(radius: f32, sweep: f32, tolerance: f32) -> u32 {
+
+    let max_step = if tolerance < radius {
+        2.0 * (1.0 - tolerance / radius).acos()
+    } else {
+        std::f32::consts::FRAC_PI_2
+    };
+
+    ((sweep.abs() / max_step).ceil() as u32).clamp(1, MAX_ARC_SEGMENTS)
+}
+
+// Points of a circular arc after the one at `start_angle`, counter
+// clockwise for a positive sweep.
+pub fn circle_arc_points(
+    center: glm::Vec2,
+    radius: f32,
+    start_angle: f32,
+    sweep: f32,
+    tolerance: f32,
+) -> Vec<glm::Vec2> {
+
+    let segments = arc_segment_count(radius, sweep, tolerance);
+
+    (1..=segments)
+        .map(|i| {
+            let (sin_t, cos_t) = (start_angle + sweep * i as f32 / segments as f32).sin_cos();
+            center + glm::vec2(cos_t, sin_t) * radius
+        })
+        .collect()
+}
+
+pub fn distance_to_line(point: &glm::Vec2, start: &glm::Vec2, end: &glm::Vec2) -> f32 {
+
+    let direction = end - start;
+    let length = glm::length(&direction);
+
+    if length < f32::EPSILON {
+        glm::distance(point, start)
+    } else {
+        glm::cross2d(&direction, &(point - start)).abs() / length
+    }
+}
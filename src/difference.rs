@@ -0,0 +1,121 @@
+use std::collections::HashSet;
+
+use nalgebra_glm as glm;
+
+use crate::{
+    common::ColorU8,
+    line::Line,
+    lines_renderer::{LinesRenderer, LineAlgorithem},
+};
+
+pub const ONLY_A_COLOR: ColorU8 = ColorU8 { r: 255, g: 70, b: 70, a: 255 };
+pub const ONLY_B_COLOR: ColorU8 = ColorU8 { r: 60, g: 150, b: 255, a: 255 };
+pub const BOTH_COLOR: ColorU8 = ColorU8 { r: 255, g: 255, b: 255, a: 255 };
+
+#[derive(Default, Clone, Copy)]
+pub struct PixelCounts {
+    pub only_a: usize,
+    pub only_b: usize,
+    pub both: usize,
+}
+
+impl PixelCounts {
+
+    fn count(a: &HashSet<glm::U16Vec2>, b: &HashSet<glm::U16Vec2>) -> Self {
+        Self {
+            only_a: a.difference(b).count(),
+            only_b: b.difference(a).count(),
+            both: a.intersection(b).count(),
+        }
+    }
+}
+
+pub struct DifferenceMap {
+    pub per_line: Vec<PixelCounts>,
+    pub total: PixelCounts,
+    pub pixels: Vec<ColorU8>,
+}
+
+pub struct DifferenceView {
+    pub enabled: bool,
+    pub algorithem_a: LineAlgorithem,
+    pub algorithem_b: LineAlgorithem,
+    map: Option<DifferenceMap>,
+}
+
+impl Default for DifferenceView {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            algorithem_a: LineAlgorithem::Bresenham,
+            algorithem_b: LineAlgorithem::DDA,
+            map: None,
+        }
+    }
+}
+
+impl DifferenceView {
+
+    pub fn get_map(&self) -> Option<&DifferenceMap> {
+        self.map.as_ref()
+    }
+
+    pub fn update(&mut self, lines: &[Line], tex_width: u16, tex_height: u16) {
+        self.map = if self.enabled {
+            Some(compute_difference(
+                lines, &self.algorithem_a, &self.algorithem_b, tex_width, tex_height
+            ))
+        } else {
+            None
+        };
+    }
+}
+
+// Rasterizes every line with both algorithms. The total counts pixels of
+// the whole scene, so pixels shared by several lines are counted once.
+pub fn compute_difference(
+    lines: &[Line],
+    algorithem_a: &LineAlgorithem,
+    algorithem_b: &LineAlgorithem,
+    tex_width: u16,
+    tex_height: u16,
+) -> DifferenceMap {
+
+    let mut scene_a = HashSet::new();
+    let mut scene_b = HashSet::new();
+    let mut per_line = Vec::with_capacity(lines.len());
+
+    for line in lines {
+
+        let a: HashSet<glm::U16Vec2> = LinesRenderer::rasterize(algorithem_a, line, tex_width, tex_height)
+            .into_iter()
+            .collect();
+        let b: HashSet<glm::U16Vec2> = LinesRenderer::rasterize(algorithem_b, line, tex_width, tex_height)
+            .into_iter()
+            .collect();
+
+        per_line.push(PixelCounts::count(&a, &b));
+
+        scene_a.extend(a);
+        scene_b.extend(b);
+    }
+
+    let mut pixels = vec![ColorU8::default(); tex_width as usize * tex_height as usize];
+
+    for pixel_pos in scene_a.union(&scene_b) {
+
+        let color = match (scene_a.contains(pixel_pos), scene_b.contains(pixel_pos)) {
+            (true, true) => &BOTH_COLOR,
+            (true, false) => &ONLY_A_COLOR,
+            _ => &ONLY_B_COLOR,
+        };
+
+        pixels[pixel_pos.y as usize * tex_width as usize + pixel_pos.x as usize] = color.clone();
+    }
+
+    DifferenceMap {
+        per_line,
+        total: PixelCounts::count(&scene_a, &scene_b),
+        pixels,
+    }
+}
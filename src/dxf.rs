@@ -0,0 +1,638 @@
+use std::{fs, collections::BTreeMap, fmt::Write};
+
+use nalgebra_glm as glm;
+
+use crate::{
+    line::Line,
+    layers::Layers,
+    curves::circle_arc_points,
+};
+
+// Entities without a layer are on this one.
+pub const DEFAULT_LAYER: &str = "0";
+
+// The units of $INSUNITS that have a length.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum DxfUnits {
+    Unitless,
+    Inches,
+    Feet,
+    Millimeters,
+    Centimeters,
+    Meters,
+}
+
+impl DxfUnits {
+
+    pub const ALL: [DxfUnits; 6] = [
+        DxfUnits::Unitless,
+        DxfUnits::Inches,
+        DxfUnits::Feet,
+        DxfUnits::Millimeters,
+        DxfUnits::Centimeters,
+        DxfUnits::Meters,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DxfUnits::Unitless => "unitless",
+            DxfUnits::Inches => "inches",
+            DxfUnits::Feet => "feet",
+            DxfUnits::Millimeters => "millimeters",
+            DxfUnits::Centimeters => "centimeters",
+            DxfUnits::Meters => "meters",
+        }
+    }
+
+    fn from_code(code: i32) -> Self {
+        match code {
+            1 => DxfUnits::Inches,
+            2 => DxfUnits::Feet,
+            4 => DxfUnits::Millimeters,
+            5 => DxfUnits::Centimeters,
+            6 => DxfUnits::Meters,
+            _ => DxfUnits::Unitless,
+        }
+    }
+
+    fn millimeters(&self) -> Option<f32> {
+        match self {
+            DxfUnits::Unitless => None,
+            DxfUnits::Inches => Some(25.4),
+            DxfUnits::Feet => Some(304.8),
+            DxfUnits::Millimeters => Some(1.0),
+            DxfUnits::Centimeters => Some(10.0),
+            DxfUnits::Meters => Some(1000.0),
+        }
+    }
+
+    // How many of these units make one of `units`. Unitless drawings are
+    // taken as they are.
+    fn per(&self, units: DxfUnits) -> f32 {
+        match (self.millimeters(), units.millimeters()) {
+            (Some(own), Some(other)) => other / own,
+            _ => 1.0,
+        }
+    }
+}
+
+// Every line comes with the name of its DXF layer.
+pub struct DxfImport {
+    pub lines: Vec<(String, Line)>,
+    pub units: DxfUnits,
+    pub layers: BTreeMap<String, usize>,
+    pub unsupported: BTreeMap<String, usize>,
+}
+
+impl DxfImport {
+
+    pub fn summary(&self) -> String {
+
+        let mut summary = format!(
+            "Imported {} lines ({})", self.lines.len(), self.units.name()
+        );
+
+        for (layer, count) in &self.layers {
+            let _ = write!(summary, "\n  layer {}: {} entities", layer, count);
+        }
+
+        if !self.unsupported.is_empty() {
+            summary.push_str("\nSkipped unsupported entities:");
+            for (entity, count) in &self.unsupported {
+                let _ = write!(summary, "\n  {}: {}", entity, count);
+            }
+        }
+
+        summary
+    }
+}
+
+// A canvas pixel is `1 / pixels_per_unit` of `units`, drawings in other
+// units are converted. The DXF y axis already points up like the canvas
+// one.
+pub fn import_dxf(
+    path: &str,
+    pixels_per_unit: f32,
+    units: DxfUnits,
+    tolerance: f32,
+) -> Result<DxfImport, String> {
+
+    let src = fs::read_to_string(path)
+        .map_err(|err| format!("Could not read {}: {}", path, err))?;
+
+    parse_dxf(&src, pixels_per_unit, units, tolerance)
+}
+
+pub fn parse_dxf(
+    src: &str,
+    pixels_per_unit: f32,
+    units: DxfUnits,
+    tolerance: f32,
+) -> Result<DxfImport, String> {
+
+    let pairs = read_group_pairs(src)?;
+
+    let mut import = DxfImport {
+        lines: Vec::new(),
+        units: DxfUnits::Unitless,
+        layers: BTreeMap::new(),
+        unsupported: BTreeMap::new(),
+    };
+
+    // Canvas pixels per drawing unit, once the header tells the units.
+    let mut scale = pixels_per_unit;
+
+    let mut section = "";
+    let mut i = 0;
+
+    while i < pairs.len() {
+
+        let (code, value) = pairs[i];
+
+        if code != 0 {
+            i += 1;
+            continue;
+        }
+
+        let entity_end = pairs[i + 1..]
+            .iter()
+            .position(|(code, _)| *code == 0)
+            .map_or(pairs.len(), |end| i + 1 + end);
+        let entity = Entity { pairs: &pairs[i + 1..entity_end] };
+
+        match value {
+
+            "SECTION" => {
+                section = entity.get(2).unwrap_or("");
+
+                if section == "HEADER" {
+                    if let Some(code) = entity.header_variable("$INSUNITS") {
+                        import.units = DxfUnits::from_code(code.parse().unwrap_or(0));
+                        scale = pixels_per_unit * units.per(import.units);
+                    }
+                }
+            }
+
+            "ENDSEC" => {
+                section = "";
+            }
+
+            "EOF" => break,
+
+            entity_type if section == "ENTITIES" => {
+
+                // Curves are flattened in drawing units.
+                let tolerance = tolerance.max(0.001) / scale;
+
+                let points = match entity_type {
+                    "LINE" => entity.line(),
+                    "LWPOLYLINE" => entity.lwpolyline(tolerance),
+                    "CIRCLE" => entity.arc(0.0, 360.0, tolerance),
+                    "ARC" => entity.arc(
+                        entity.get_f32(50).unwrap_or(0.0),
+                        entity.get_f32(51).unwrap_or(360.0),
+                        tolerance,
+                    ),
+                    _ => None,
+                };
+
+                match points {
+                    Some(points) => {
+
+                        let layer = entity.get(8).unwrap_or(DEFAULT_LAYER);
+
+                        for segment in points.windows(2) {
+                            if segment[0] != segment[1] {
+                                import.lines.push((
+                                    layer.to_string(),
+                                    Line::new(segment[0] * scale, segment[1] * scale),
+                                ));
+                            }
+                        }
+
+                        *import.layers.entry(layer.to_string()).or_insert(0) += 1;
+                    }
+                    None => {
+                        *import.unsupported.entry(entity_type.to_string()).or_insert(0) += 1;
+                    }
+                }
+            }
+
+            _ => {}
+        }
+
+        i = entity_end;
+    }
+
+    Ok(import)
+}
+
+pub fn export_dxf(
+    path: &str,
+    lines: &[Line],
+    layers: &Layers,
+    pixels_per_unit: f32,
+) -> Result<(), String> {
+
+    fs::write(path, write_dxf(lines, layers, pixels_per_unit))
+        .map_err(|err| format!("Could not write {}: {}", path, err))
+}
+
+// Every layer goes in the layer table, hidden ones with a negative color
+// as DXF turns layers off. R12 has no $INSUNITS, that came with R2000, so
+// the drawing does not say its units and imports as unitless.
+pub fn write_dxf(lines: &[Line], layers: &Layers, pixels_per_unit: f32) -> String {
+
+    let mut dxf = String::new();
+    let mut pair = |code: i32, value: &str| {
+        let _ = write!(dxf, "{:>3}\n{}\n", code, value);
+    };
+
+    pair(0, "SECTION");
+    pair(2, "HEADER");
+    pair(9, "$ACADVER");
+    pair(1, "AC1009");
+    pair(0, "ENDSEC");
+
+    pair(0, "SECTION");
+    pair(2, "TABLES");
+    pair(0, "TABLE");
+    pair(2, "LAYER");
+    pair(70, &layers.get_layers().len().to_string());
+    for layer in layers.get_layers() {
+        pair(0, "LAYER");
+        pair(2, &layer_name(&layer.name));
+        pair(70, "0");
+        pair(62, if layer.visible { "7" } else { "-7" });
+        pair(6, "CONTINUOUS");
+    }
+    pair(0, "ENDTAB");
+    pair(0, "ENDSEC");
+
+    pair(0, "SECTION");
+    pair(2, "ENTITIES");
+
+    for line in lines {
+
+        let start = line.start / pixels_per_unit;
+        let end = line.end / pixels_per_unit;
+
+        let layer = match layers.get(line.layer) {
+            Some(layer) => layer_name(&layer.name),
+            None => DEFAULT_LAYER.to_string(),
+        };
+
+        pair(0, "LINE");
+        pair(8, &layer);
+        pair(10, &start.x.to_string());
+        pair(20, &start.y.to_string());
+        pair(30, "0.0");
+        pair(11, &end.x.to_string());
+        pair(21, &end.y.to_string());
+        pair(31, "0.0");
+    }
+
+    pair(0, "ENDSEC");
+    pair(0, "EOF");
+
+    dxf
+}
+
+fn read_group_pairs(src: &str) -> Result<Vec<(i32, &str)>, String> {
+
+    let mut lines = src.lines();
+    let mut pairs = Vec::new();
+
+    while let Some(code) = lines.next() {
+
+        let code = code.trim();
+
+        if code.is_empty() {
+            continue;
+        }
+
+        let code = code.parse::<i32>()
+            .map_err(|_| format!("Invalid DXF group code: {}", code))?;
+        let value = lines.next()
+            .ok_or_else(|| format!("Missing value for DXF group code {}", code))?;
+
+        pairs.push((code, value.trim()));
+    }
+
+    Ok(pairs)
+}
+
+// DXF layer names can not be empty or hold some characters.
+fn layer_name(name: &str) -> String {
+
+    let name: String = name
+        .trim()
+        .chars()
+        .map(|c| if "<>/\\\":;?*|=`".contains(c) { '_' } else { c })
+        .collect();
+
+    if name.is_empty() { DEFAULT_LAYER.to_string() } else { name }
+}
+
+struct Entity<'a> {
+    pairs: &'a [(i32, &'a str)],
+}
+
+impl<'a> Entity<'a> {
+
+    fn get(&self, code: i32) -> Option<&'a str> {
+        self.pairs
+            .iter()
+            .find(|(c, _)| *c == code)
+            .map(|(_, value)| *value)
+    }
+
+    fn header_variable(&self, name: &str) -> Option<&'a str> {
+        let index = self.pairs.iter().position(|pair| *pair == (9, name))?;
+        self.pairs.get(index + 1).map(|(_, value)| *value)
+    }
+
+    fn get_f32(&self, code: i32) -> Option<f32> {
+        self.get(code).and_then(|value| value.parse().ok())
+    }
+
+    fn line(&self) -> Option<Vec<glm::Vec2>> {
+        Some(vec![
+            glm::vec2(self.get_f32(10)?, self.get_f32(20)?),
+            glm::vec2(self.get_f32(11)?, self.get_f32(21)?),
+        ])
+    }
+
+    fn arc(&self, start_deg: f32, end_deg: f32, tolerance: f32) -> Option<Vec<glm::Vec2>> {
+
+        let center = glm::vec2(self.get_f32(10)?, self.get_f32(20)?);
+        let radius = self.get_f32(40)?;
+
+        let start = start_deg.to_radians();
+        let mut sweep = (end_deg - start_deg).to_radians();
+        if sweep <= 0.0 {
+            sweep += 2.0 * std::f32::consts::PI;
+        }
+
+        let mut points = vec![center + glm::vec2(start.cos(), start.sin()) * radius];
+        points.extend(circle_arc_points(center, radius, start, sweep, tolerance));
+
+        Some(points)
+    }
+
+    // Vertices come as repeated 10/20 pairs, each optionally followed by a
+    // bulge (42), the tangent of a quarter of the arc's included angle.
+    fn lwpolyline(&self, tolerance: f32) -> Option<Vec<glm::Vec2>> {
+
+        let closed = self.get(70)
+            .and_then(|flags| flags.parse::<i32>().ok())
+            .is_some_and(|flags| flags & 1 != 0);
+
+        let mut vertices: Vec<(glm::Vec2, f32)> = Vec::new();
+
+        for (code, value) in self.pairs {
+            match code {
+                10 => vertices.push((glm::vec2(value.parse().ok()?, 0.0), 0.0)),
+                20 => vertices.last_mut()?.0.y = value.parse().ok()?,
+                42 => vertices.last_mut()?.1 = value.parse().ok()?,
+                _ => {}
+            }
+        }
+
+        if vertices.is_empty() {
+            return None;
+        }
+
+        if closed {
+            vertices.push((vertices[0].0, 0.0));
+        }
+
+        let mut points = vec![vertices[0].0];
+
+        for pair in vertices.windows(2) {
+
+            let ((start, bulge), (end, _)) = (pair[0], pair[1]);
+
+            if bulge.abs() < f32::EPSILON || start == end {
+                points.push(end);
+                continue;
+            }
+
+            let chord = end - start;
+            let sagitta = bulge * glm::length(&chord) / 2.0;
+            let radius = (glm::length2(&chord) / 4.0 + sagitta * sagitta) / (2.0 * sagitta);
+            let left = glm::normalize(&glm::vec2(-chord.y, chord.x));
+            let center = (start + end) * 0.5 + left * (radius - sagitta);
+
+            let from_center = start - center;
+            let start_angle = from_center.y.atan2(from_center.x);
+            let sweep = 4.0 * bulge.atan();
+
+            let mut arc = circle_arc_points(
+                center, radius.abs(), start_angle, sweep, tolerance
+            );
+            arc.pop();
+            points.extend(arc);
+            points.push(end);
+        }
+
+        Some(points)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    const EPSILON: f32 = 1e-3;
+
+    fn to_dxf(header: &[(i32, &str)], entities: &[(i32, &str)]) -> String {
+
+        let mut dxf = String::new();
+        let mut write_pairs = |pairs: &[(i32, &str)]| {
+            for (code, value) in pairs {
+                let _ = write!(dxf, "{}\n{}\n", code, value);
+            }
+        };
+
+        write_pairs(&[(0, "SECTION"), (2, "HEADER")]);
+        write_pairs(header);
+        write_pairs(&[(0, "ENDSEC"), (0, "SECTION"), (2, "ENTITIES")]);
+        write_pairs(entities);
+        write_pairs(&[(0, "ENDSEC"), (0, "EOF")]);
+
+        dxf
+    }
+
+    fn parse(entities: &[(i32, &str)]) -> DxfImport {
+        parse_dxf(&to_dxf(&[], entities), 1.0, DxfUnits::Unitless, 0.1).unwrap()
+    }
+
+    fn import_points(import: &DxfImport) -> Vec<glm::Vec2> {
+        let mut points = vec![import.lines[0].1.start];
+        points.extend(import.lines.iter().map(|(_, line)| line.end));
+        points
+    }
+
+    fn assert_close(a: &glm::Vec2, b: &glm::Vec2) {
+        assert!(glm::distance(a, b) < EPSILON, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn line_keeps_its_layer() {
+        let import = parse(&[
+            (0, "LINE"), (8, "walls"),
+            (10, "1"), (20, "2"), (30, "0"), (11, "3"), (21, "-4"), (31, "0"),
+        ]);
+
+        assert_eq!(import.lines.len(), 1);
+        let (layer, line) = &import.lines[0];
+        assert_eq!(layer, "walls");
+        assert_close(&line.start, &glm::vec2(1.0, 2.0));
+        assert_close(&line.end, &glm::vec2(3.0, -4.0));
+        assert_eq!(import.layers.get("walls"), Some(&1));
+    }
+
+    #[test]
+    fn entities_without_a_layer_are_on_the_default_one() {
+        let import = parse(&[(0, "LINE"), (10, "0"), (20, "0"), (11, "1"), (21, "1")]);
+        assert_eq!(import.lines[0].0, DEFAULT_LAYER);
+    }
+
+    #[test]
+    fn closed_lwpolyline_returns_to_its_start() {
+        let import = parse(&[
+            (0, "LWPOLYLINE"), (90, "3"), (70, "1"),
+            (10, "0"), (20, "0"), (10, "4"), (20, "0"), (10, "4"), (20, "3"),
+        ]);
+
+        assert_eq!(import.lines.len(), 3);
+        let points = import_points(&import);
+        assert_close(&points[1], &glm::vec2(4.0, 0.0));
+        assert_close(&points[2], &glm::vec2(4.0, 3.0));
+        assert_close(&points[3], &glm::vec2(0.0, 0.0));
+    }
+
+    #[test]
+    fn lwpolyline_bulge_makes_an_arc() {
+        // A bulge of 1 is a half circle, counterclockwise from the start.
+        let import = parse(&[
+            (0, "LWPOLYLINE"), (90, "2"), (70, "0"),
+            (10, "0"), (20, "0"), (42, "1"), (10, "2"), (20, "0"),
+        ]);
+
+        let points = import_points(&import);
+        assert!(points.len() > 4);
+        assert_close(&points[0], &glm::vec2(0.0, 0.0));
+        assert_close(points.last().unwrap(), &glm::vec2(2.0, 0.0));
+        for point in &points {
+            assert!((glm::distance(point, &glm::vec2(1.0, 0.0)) - 1.0).abs() < EPSILON);
+            assert!(point.y <= EPSILON);
+        }
+
+        // A negative bulge goes the other way.
+        let import = parse(&[
+            (0, "LWPOLYLINE"), (90, "2"),
+            (10, "0"), (20, "0"), (42, "-1"), (10, "2"), (20, "0"),
+        ]);
+        assert!(import_points(&import).iter().all(|point| point.y >= -EPSILON));
+    }
+
+    #[test]
+    fn circle_is_closed_and_on_its_radius() {
+        let import = parse(&[(0, "CIRCLE"), (10, "5"), (20, "5"), (40, "10")]);
+
+        let points = import_points(&import);
+        assert!(points.len() > 8);
+        assert_close(&points[0], points.last().unwrap());
+        for point in &points {
+            assert!((glm::distance(point, &glm::vec2(5.0, 5.0)) - 10.0).abs() < EPSILON);
+        }
+    }
+
+    #[test]
+    fn arc_goes_counterclockwise_between_its_angles() {
+        let import = parse(&[
+            (0, "ARC"), (10, "0"), (20, "0"), (40, "10"), (50, "0"), (51, "90"),
+        ]);
+
+        let points = import_points(&import);
+        assert_close(&points[0], &glm::vec2(10.0, 0.0));
+        assert_close(points.last().unwrap(), &glm::vec2(0.0, 10.0));
+        assert!(points.iter().all(|point| point.x >= -EPSILON && point.y >= -EPSILON));
+
+        // Ending before the start angle wraps around.
+        let import = parse(&[
+            (0, "ARC"), (10, "0"), (20, "0"), (40, "10"), (50, "270"), (51, "0"),
+        ]);
+        let points = import_points(&import);
+        assert_close(&points[0], &glm::vec2(0.0, -10.0));
+        assert_close(points.last().unwrap(), &glm::vec2(10.0, 0.0));
+        assert!(points.iter().all(|point| point.x >= -EPSILON && point.y <= EPSILON));
+    }
+
+    #[test]
+    fn unsupported_entities_are_counted() {
+        let import = parse(&[
+            (0, "SPLINE"), (8, "0"),
+            (0, "TEXT"), (1, "hello"),
+            (0, "TEXT"), (1, "again"),
+            // A line without its end can not be read either.
+            (0, "LINE"), (10, "0"), (20, "0"),
+        ]);
+
+        assert!(import.lines.is_empty());
+        assert_eq!(import.unsupported.get("SPLINE"), Some(&1));
+        assert_eq!(import.unsupported.get("TEXT"), Some(&2));
+        assert_eq!(import.unsupported.get("LINE"), Some(&1));
+    }
+
+    #[test]
+    fn units_are_converted() {
+        let dxf = to_dxf(
+            &[(9, "$INSUNITS"), (70, "4")],
+            &[(0, "LINE"), (10, "0"), (20, "0"), (11, "10"), (21, "0")],
+        );
+
+        // Two pixels per centimeter.
+        let import = parse_dxf(&dxf, 2.0, DxfUnits::Centimeters, 0.1).unwrap();
+        assert_eq!(import.units, DxfUnits::Millimeters);
+        assert_close(&import.lines[0].1.end, &glm::vec2(2.0, 0.0));
+
+        // Unitless settings take the drawing as it is.
+        let import = parse_dxf(&dxf, 2.0, DxfUnits::Unitless, 0.1).unwrap();
+        assert_close(&import.lines[0].1.end, &glm::vec2(20.0, 0.0));
+    }
+
+    #[test]
+    fn export_round_trips_layers() {
+        let mut layers = Layers::default();
+        let mut doors = layers.new_layer();
+        doors.name = "doors".to_string();
+        let doors_id = doors.id;
+        layers.insert(1, doors);
+
+        let mut door = Line::new(glm::vec2(0.0, 0.0), glm::vec2(10.0, 20.0));
+        door.layer = doors_id;
+        let wall = Line::new(glm::vec2(-5.0, 0.0), glm::vec2(5.0, 0.0));
+
+        let dxf = write_dxf(&[door, wall], &layers, 10.0);
+        let import = parse_dxf(&dxf, 10.0, DxfUnits::Inches, 0.1).unwrap();
+
+        // Read back with the same settings it was written with.
+        assert!(!dxf.contains("$INSUNITS"));
+        assert_eq!(import.units, DxfUnits::Unitless);
+        assert_eq!(import.lines.len(), 2);
+        assert_eq!(import.lines[0].0, "doors");
+        assert_close(&import.lines[0].1.end, &glm::vec2(10.0, 20.0));
+        assert_eq!(import.lines[1].0, "Layer 0");
+        assert_close(&import.lines[1].1.start, &glm::vec2(-5.0, 0.0));
+    }
+
+    #[test]
+    fn layer_names_are_made_valid() {
+        assert_eq!(layer_name("a/b:c"), "a_b_c");
+        assert_eq!(layer_name("  "), DEFAULT_LAYER);
+    }
+}
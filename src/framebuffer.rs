@@ -73,6 +73,15 @@ impl Framebuffer {
         }
     }
 
+    // Like bind but drawing only to a region, in framebuffer pixels from
+    // the top left.
+    pub fn bind_region(&self, x: i32, y: i32, width: i32, height: i32) {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.id);
+            gl::Viewport(x, self.height as i32 - y - height, width, height);
+        }
+    }
+
     pub fn unbind(&self) {
         unsafe {
             gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
@@ -87,6 +96,17 @@ impl Framebuffer {
         }
     }
 
+    // The default framebuffer only keeps track of the new size.
+    pub fn resize(&mut self, width: u16, height: u16) {
+        if self.color_attachment.is_some() {
+            *self = Self::new(width, height);
+        }
+        else {
+            self.width = width;
+            self.height = height;
+        }
+    }
+
     pub fn get_size(&self) -> (u16, u16) {
         (self.width, self.height)
     }
@@ -112,6 +132,38 @@ impl Framebuffer {
             None => {}
         }
     }
+
+    // Rows from the bottom one, like the color data is set.
+    pub fn read_color_data(&self) -> Vec<ColorU8> {
+        self.read_region(0, 0, self.width, self.height)
+    }
+
+    pub fn read_pixel(&self, x: u16, y: u16) -> ColorU8 {
+        self.read_region(x, y, 1, 1).remove(0)
+    }
+
+    fn read_region(&self, x: u16, y: u16, width: u16, height: u16) -> Vec<ColorU8> {
+
+        let mut data = vec![0_u8; width as usize * height as usize * 4];
+
+        self.bind();
+        unsafe {
+            gl::ReadPixels(
+                x as i32,
+                y as i32,
+                width as i32,
+                height as i32,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                data.as_mut_ptr().cast()
+            );
+        }
+        self.unbind();
+
+        data.chunks(4)
+            .map(|color| ColorU8 { r: color[0], g: color[1], b: color[2], a: color[3] })
+            .collect()
+    }
 }
 
 impl Drop for Framebuffer {
@@ -0,0 +1,421 @@
+use std::collections::HashMap;
+
+use crate::common::ColorU8;
+
+const MAX_PALETTE_LEN: usize = 256;
+const MAX_CODE: u16 = 4096;
+
+// Writes the frames one at a time, so they do not all have to be held at
+// the size they are saved at. Rows go from top to bottom. All the frames
+// share one palette, quantized with median cut when they have more than
+// 256 colors. The animation loops forever.
+pub struct GifEncoder {
+    gif: Vec<u8>,
+    indices: HashMap<[u8; 3], u8>,
+    min_code_size: u8,
+    width: u16,
+    height: u16,
+}
+
+impl GifEncoder {
+
+    // The palette is made from `palette_frames`, at any scale, which have
+    // to hold every color of the frames added later.
+    pub fn new(palette_frames: &[&[ColorU8]], width: u16, height: u16) -> Self {
+
+        let mut histogram = HashMap::new();
+        for frame in palette_frames {
+            for color in frame.iter() {
+                *histogram.entry([color.r, color.g, color.b]).or_insert(0_usize) += 1;
+            }
+        }
+
+        let palette = quantize(&histogram);
+        let indices = palette_indices(&histogram, &palette);
+
+        // The color table has a power of two length of at least 2.
+        let mut table_bits = 1;
+        while (1 << table_bits) < palette.len() {
+            table_bits += 1;
+        }
+
+        let mut gif = Vec::new();
+
+        gif.extend_from_slice(b"GIF89a");
+        gif.extend_from_slice(&width.to_le_bytes());
+        gif.extend_from_slice(&height.to_le_bytes());
+        gif.push(0x80 | (7 << 4) | (table_bits - 1));
+        gif.push(0);
+        gif.push(0);
+
+        for i in 0..(1 << table_bits) {
+            gif.extend_from_slice(palette.get(i).unwrap_or(&[0, 0, 0]));
+        }
+
+        // Netscape extension, loops forever.
+        gif.extend_from_slice(&[0x21, 0xFF, 0x0B]);
+        gif.extend_from_slice(b"NETSCAPE2.0");
+        gif.extend_from_slice(&[0x03, 0x01, 0x00, 0x00, 0x00]);
+
+        Self {
+            gif,
+            indices,
+            min_code_size: table_bits.max(2),
+            width,
+            height,
+        }
+    }
+
+    // `delay` is in hundredths of a second.
+    pub fn add_frame(&mut self, frame: &[ColorU8], delay: u16) {
+
+        let gif = &mut self.gif;
+
+        // Graphic control extension with the frame delay.
+        gif.extend_from_slice(&[0x21, 0xF9, 0x04, 0x00]);
+        gif.extend_from_slice(&delay.to_le_bytes());
+        gif.extend_from_slice(&[0x00, 0x00]);
+
+        // Image descriptor covering the whole screen.
+        gif.push(0x2C);
+        gif.extend_from_slice(&0_u16.to_le_bytes());
+        gif.extend_from_slice(&0_u16.to_le_bytes());
+        gif.extend_from_slice(&self.width.to_le_bytes());
+        gif.extend_from_slice(&self.height.to_le_bytes());
+        gif.push(0);
+
+        // Colors missing from the palette frames get the first entry.
+        let frame_indices: Vec<u8> = frame
+            .iter()
+            .map(|color| self.indices.get(&[color.r, color.g, color.b]).copied().unwrap_or(0))
+            .collect();
+
+        gif.push(self.min_code_size);
+        for block in lzw_encode(&frame_indices, self.min_code_size).chunks(255) {
+            gif.push(block.len() as u8);
+            gif.extend_from_slice(block);
+        }
+        gif.push(0);
+    }
+
+    pub fn finish(mut self) -> Vec<u8> {
+        self.gif.push(0x3B);
+        self.gif
+    }
+}
+
+// Keeps the exact colors when they fit, otherwise splits the color space
+// in boxes along the widest channel at the median pixel and takes the
+// average of every box.
+fn quantize(histogram: &HashMap<[u8; 3], usize>) -> Vec<[u8; 3]> {
+
+    let mut colors: Vec<([u8; 3], usize)> = histogram
+        .iter()
+        .map(|(color, count)| (*color, *count))
+        .collect();
+    colors.sort_unstable();
+
+    if colors.len() <= MAX_PALETTE_LEN {
+        return colors.into_iter().map(|(color, _)| color).collect();
+    }
+
+    let mut boxes = vec![colors];
+
+    while boxes.len() < MAX_PALETTE_LEN {
+
+        let widest = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, colors)| colors.len() > 1)
+            .map(|(i, colors)| (i, widest_channel(colors)))
+            .max_by_key(|(_, (_, range))| *range);
+
+        let (i, (channel, _)) = match widest {
+            Some(widest) => widest,
+            None => break,
+        };
+
+        let mut colors = boxes.swap_remove(i);
+        colors.sort_unstable_by_key(|(color, _)| color[channel]);
+
+        let total: usize = colors.iter().map(|(_, count)| count).sum();
+        let mut accumulated = 0;
+        let mut median = 1;
+        for (j, (_, count)) in colors.iter().enumerate() {
+            accumulated += count;
+            if accumulated * 2 >= total {
+                median = (j + 1).clamp(1, colors.len() - 1);
+                break;
+            }
+        }
+
+        let upper = colors.split_off(median);
+        boxes.push(colors);
+        boxes.push(upper);
+    }
+
+    boxes
+        .iter()
+        .map(|colors| {
+            let total: usize = colors.iter().map(|(_, count)| count).sum();
+            let mut sum = [0_usize; 3];
+            for (color, count) in colors {
+                for channel in 0..3 {
+                    sum[channel] += color[channel] as usize * count;
+                }
+            }
+            [
+                ((sum[0] + total / 2) / total) as u8,
+                ((sum[1] + total / 2) / total) as u8,
+                ((sum[2] + total / 2) / total) as u8,
+            ]
+        })
+        .collect()
+}
+
+fn widest_channel(colors: &[([u8; 3], usize)]) -> (usize, u8) {
+    (0..3)
+        .map(|channel| {
+            let min = colors.iter().map(|(color, _)| color[channel]).min().unwrap_or(0);
+            let max = colors.iter().map(|(color, _)| color[channel]).max().unwrap_or(0);
+            (channel, max - min)
+        })
+        .max_by_key(|(_, range)| *range)
+        .unwrap_or((0, 0))
+}
+
+fn palette_indices(
+    histogram: &HashMap<[u8; 3], usize>,
+    palette: &[[u8; 3]],
+) -> HashMap<[u8; 3], u8> {
+
+    histogram
+        .keys()
+        .map(|color| {
+            let distance = |entry: &[u8; 3]| -> i32 {
+                (0..3)
+                    .map(|channel| (color[channel] as i32 - entry[channel] as i32).pow(2))
+                    .sum()
+            };
+            let index = (0..palette.len())
+                .min_by_key(|&i| distance(&palette[i]))
+                .unwrap_or(0);
+            (*color, index as u8)
+        })
+        .collect()
+}
+
+// Variable code width LZW as the GIF format wants it, the table is cleared
+// once it is full.
+fn lzw_encode(indices: &[u8], min_code_size: u8) -> Vec<u8> {
+
+    let clear_code = 1_u16 << min_code_size;
+    let end_code = clear_code + 1;
+
+    let mut writer = BitWriter::default();
+    let mut table: HashMap<(u16, u8), u16> = HashMap::new();
+    let mut code_size = min_code_size + 1;
+    let mut next_code = end_code + 1;
+
+    writer.write(clear_code, code_size);
+
+    let mut prefix = match indices.first() {
+        Some(index) => *index as u16,
+        None => {
+            writer.write(end_code, code_size);
+            return writer.finish();
+        }
+    };
+
+    for &index in &indices[1..] {
+
+        if let Some(&code) = table.get(&(prefix, index)) {
+            prefix = code;
+            continue;
+        }
+
+        writer.write(prefix, code_size);
+
+        // The decoder adds its entries a code later, so the width grows once
+        // the next code does not fit.
+        if next_code >= 1 << code_size && code_size < 12 {
+            code_size += 1;
+        }
+
+        if next_code < MAX_CODE {
+            table.insert((prefix, index), next_code);
+            next_code += 1;
+        }
+        else {
+            writer.write(clear_code, code_size);
+            table.clear();
+            code_size = min_code_size + 1;
+            next_code = end_code + 1;
+        }
+
+        prefix = index as u16;
+    }
+
+    writer.write(prefix, code_size);
+    if next_code >= 1 << code_size && code_size < 12 {
+        code_size += 1;
+    }
+    writer.write(end_code, code_size);
+
+    writer.finish()
+}
+
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u32,
+    bit_count: u8,
+}
+
+impl BitWriter {
+
+    // Least significant bits first.
+    fn write(&mut self, code: u16, size: u8) {
+        self.buffer |= (code as u32) << self.bit_count;
+        self.bit_count += size;
+        while self.bit_count >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.bit_count -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bit_count > 0 {
+            self.bytes.push(self.buffer as u8);
+        }
+        self.bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    // Reads the codes back the way a GIF decoder does.
+    fn lzw_decode(bytes: &[u8], min_code_size: u8) -> Vec<u8> {
+
+        let clear_code = 1_u16 << min_code_size;
+        let end_code = clear_code + 1;
+
+        let mut bit_pos = 0;
+        let mut read = |size: u8| {
+            let mut code = 0_u16;
+            for bit in 0..size as usize {
+                let byte = bytes[(bit_pos + bit) / 8];
+                code |= (((byte >> ((bit_pos + bit) % 8)) & 1) as u16) << bit;
+            }
+            bit_pos += size as usize;
+            code
+        };
+
+        let initial_table = || -> Vec<Vec<u8>> {
+            (0..=end_code).map(|code| vec![code as u8]).collect()
+        };
+
+        let mut table = initial_table();
+        let mut code_size = min_code_size + 1;
+        let mut previous: Option<Vec<u8>> = None;
+        let mut output = Vec::new();
+
+        loop {
+            let code = read(code_size);
+
+            if code == clear_code {
+                table = initial_table();
+                code_size = min_code_size + 1;
+                previous = None;
+                continue;
+            }
+            if code == end_code {
+                break;
+            }
+
+            let entry = match (table.get(code as usize), &previous) {
+                (Some(entry), _) => entry.clone(),
+                (None, Some(previous)) => {
+                    let mut entry = previous.clone();
+                    entry.push(previous[0]);
+                    entry
+                }
+                (None, None) => panic!("code {} before any entry", code),
+            };
+
+            if let Some(previous) = previous {
+                if table.len() < MAX_CODE as usize {
+                    let mut new_entry = previous;
+                    new_entry.push(entry[0]);
+                    table.push(new_entry);
+                }
+            }
+            if table.len() >= 1 << code_size && code_size < 12 {
+                code_size += 1;
+            }
+
+            output.extend_from_slice(&entry);
+            previous = Some(entry);
+        }
+
+        output
+    }
+
+    fn assert_round_trip(indices: &[u8], min_code_size: u8) {
+        let decoded = lzw_decode(&lzw_encode(indices, min_code_size), min_code_size);
+        assert_eq!(decoded, indices);
+    }
+
+    #[test]
+    fn lzw_round_trips_short_runs() {
+        assert_round_trip(&[], 2);
+        assert_round_trip(&[1], 2);
+        assert_round_trip(&[0, 1, 0, 1, 0, 1, 0, 1, 2, 3], 2);
+        assert_round_trip(&[3; 100], 2);
+    }
+
+    #[test]
+    fn lzw_round_trips_through_table_resets() {
+        // Pseudo random indices fill the table many times over.
+        let mut state = 12345_u32;
+        let indices: Vec<u8> = (0..50_000)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                (state >> 16) as u8
+            })
+            .collect();
+
+        assert_round_trip(&indices, 8);
+        assert_round_trip(&indices.iter().map(|index| index % 4).collect::<Vec<u8>>(), 2);
+    }
+
+    #[test]
+    fn quantize_keeps_few_colors_and_limits_many() {
+        let few: HashMap<[u8; 3], usize> = [([0, 0, 0], 3), ([255, 0, 0], 1)].into_iter().collect();
+        assert_eq!(quantize(&few), vec![[0, 0, 0], [255, 0, 0]]);
+
+        let many: HashMap<[u8; 3], usize> = (0..1000_u32)
+            .map(|i| ([(i % 256) as u8, (i / 256 * 60) as u8, (i * 7 % 256) as u8], 1))
+            .collect();
+        assert_eq!(quantize(&many).len(), MAX_PALETTE_LEN);
+    }
+
+    #[test]
+    fn encoder_writes_a_complete_file() {
+        let black = ColorU8 { r: 0, g: 0, b: 0, a: 255 };
+        let white = ColorU8 { r: 255, g: 255, b: 255, a: 255 };
+        let frame = vec![black, white.clone(), white.clone(), white];
+
+        let mut encoder = GifEncoder::new(&[&frame], 2, 2);
+        encoder.add_frame(&frame, 10);
+        let gif = encoder.finish();
+
+        assert_eq!(&gif[..6], b"GIF89a");
+        assert_eq!(&gif[6..10], &[2, 0, 2, 0]);
+        assert_eq!(gif.last(), Some(&0x3B));
+    }
+}
@@ -0,0 +1,174 @@
+use nalgebra_glm as glm;
+
+use crate::{
+    scene_graph::{SceneGraph, Transform, transform_point, transform_vector, inverse_or_identity},
+    history::Command,
+};
+
+// In screen pixels, divided by the zoom to draw the gizmo.
+pub const GIZMO_SIZE: f32 = 60.0_f32;
+// Drags closer than this to the origin can not tell a direction.
+const MIN_ARM: f32 = 1e-3_f32;
+
+#[derive(PartialEq, Clone, Copy)]
+pub enum GizmoMode {
+    Move,
+    Rotate,
+    Scale,
+    Shear,
+}
+
+impl GizmoMode {
+
+    pub const ALL: [GizmoMode; 4] = [
+        GizmoMode::Move,
+        GizmoMode::Rotate,
+        GizmoMode::Scale,
+        GizmoMode::Shear,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            GizmoMode::Move => "Move",
+            GizmoMode::Rotate => "Rotate",
+            GizmoMode::Scale => "Scale",
+            GizmoMode::Shear => "Shear",
+        }
+    }
+}
+
+struct GizmoDrag {
+    group: u32,
+    // In the coordinates of the group's parent.
+    grab_pos: glm::Vec2,
+    original: Transform,
+}
+
+// Dragging anywhere on the canvas edits the transform of the selected
+// group, relative to its origin.
+pub struct Gizmo {
+    pub mode: GizmoMode,
+    drag: Option<GizmoDrag>,
+}
+
+impl Default for Gizmo {
+    fn default() -> Self {
+        Self {
+            mode: GizmoMode::Move,
+            drag: None,
+        }
+    }
+}
+
+impl Gizmo {
+
+    pub fn is_dragging(&self) -> bool {
+        self.drag.is_some()
+    }
+
+    pub fn press(&mut self, pos: &glm::Vec2, graph: &SceneGraph) {
+
+        self.drag = graph.get_selected().and_then(|id| graph.get_group(id)).map(|group| GizmoDrag {
+            group: group.id,
+            grab_pos: to_parent(graph, group.id, pos),
+            original: group.transform.clone(),
+        });
+    }
+
+    pub fn drag(&mut self, pos: &glm::Vec2, graph: &mut SceneGraph) {
+
+        let drag = match &self.drag {
+            Some(drag) => drag,
+            None => return,
+        };
+
+        let pos = to_parent(graph, drag.group, pos);
+        let original = &drag.original;
+        let origin = original.get_origin();
+        let (grab_arm, arm) = (drag.grab_pos - origin, pos - origin);
+
+        let mut transform = original.clone();
+
+        match self.mode {
+
+            GizmoMode::Move => {
+                transform.translation = original.translation + (pos - drag.grab_pos);
+            }
+
+            GizmoMode::Rotate => {
+                if glm::length(&grab_arm) > MIN_ARM && glm::length(&arm) > MIN_ARM {
+                    let turn = arm.y.atan2(arm.x) - grab_arm.y.atan2(grab_arm.x);
+                    transform.rotation = original.rotation + turn.to_degrees();
+                }
+            }
+
+            // In the group's axes before scaling, where scaling only
+            // stretches each axis.
+            GizmoMode::Scale => {
+                let to_axes = inverse_or_identity(&original.rotation_shear_matrix());
+                let (grab_arm, arm) = (transform_vector(&to_axes, &grab_arm), transform_vector(&to_axes, &arm));
+                for axis in 0..2 {
+                    if grab_arm[axis].abs() > MIN_ARM {
+                        transform.scale[axis] = original.scale[axis] * arm[axis] / grab_arm[axis];
+                    }
+                }
+            }
+
+            // Moving across an axis shears along the other one, by the
+            // distance moved over the distance to that axis.
+            GizmoMode::Shear => {
+                let unrotate = glm::rotate2d(&glm::Mat3::identity(), -original.rotation.to_radians());
+                let moved = transform_vector(&unrotate, &(pos - drag.grab_pos));
+                let to_axes = inverse_or_identity(&original.rotation_shear_matrix());
+                let grab_arm = transform_vector(&to_axes, &grab_arm);
+                // The x shear applies after the y one.
+                let sheared_y = grab_arm.y + original.shear.y * grab_arm.x;
+                if sheared_y.abs() > MIN_ARM {
+                    transform.shear.x = original.shear.x + moved.x / sheared_y;
+                }
+                if grab_arm.x.abs() > MIN_ARM {
+                    transform.shear.y = original.shear.y + moved.y / grab_arm.x;
+                }
+            }
+        }
+
+        if let Some(group) = graph.get_group_mut(drag.group) {
+            group.transform = transform;
+        }
+    }
+
+    // The group was already transformed while dragging, the returned
+    // command records it for the history.
+    pub fn release(&mut self, graph: &SceneGraph) -> Option<Command> {
+
+        let drag = self.drag.take()?;
+        let after = graph.get_group(drag.group)?.clone();
+
+        if after.transform == drag.original {
+            return None;
+        }
+
+        let mut before = after.clone();
+        before.transform = drag.original;
+
+        Some(Command::EditGroup { before, after })
+    }
+}
+
+// The origin of a group and its x and y axes, on the canvas.
+pub fn gizmo_axes(graph: &SceneGraph, id: u32) -> Option<(glm::Vec2, glm::Vec2, glm::Vec2)> {
+
+    let group = graph.get_group(id)?;
+    let parent = graph.parent_matrix(id);
+    let linear = parent * group.transform.linear_matrix();
+
+    Some((
+        transform_point(&parent, &group.transform.get_origin()),
+        transform_vector(&linear, &glm::vec2(1.0, 0.0)),
+        transform_vector(&linear, &glm::vec2(0.0, 1.0)),
+    ))
+}
+
+fn to_parent(graph: &SceneGraph, id: u32, pos: &glm::Vec2) -> glm::Vec2 {
+    transform_point(&inverse_or_identity(&graph.parent_matrix(id)), pos)
+}
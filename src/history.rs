@@ -0,0 +1,563 @@
+use std::collections::VecDeque;
+
+use crate::{
+    line::Line,
+    layers::{Layers, Layer},
+    scene_graph::{SceneGraph, Group},
+};
+
+pub const DEFAULT_MAX_HISTORY_SIZE: usize = 100;
+
+pub enum Command {
+    AddLines { index: usize, lines: Vec<Line> },
+    DeleteLine { index: usize, line: Line },
+    EditLine { index: usize, before: Line, after: Line },
+    MoveLine { from: usize, to: usize },
+    // The lines removed and where they were, in order.
+    Clear { lines: Vec<(usize, Line)> },
+    // `position` counts from the bottom layer.
+    AddLayer { position: usize, layer: Layer },
+    RemoveLayer { position: usize, layer: Layer },
+    AddGroup { position: usize, group: Group },
+    RemoveGroup { position: usize, group: Group },
+    // The name, parent or transform of a group, which keeps its id.
+    EditGroup { before: Group, after: Group },
+    // Applied in order, undone as a single step.
+    Batch { commands: Vec<Command> },
+}
+
+impl Command {
+
+    pub fn add_line(lines: &[Line], line: Line) -> Self {
+        Command::AddLines { index: lines.len(), lines: vec![line] }
+    }
+
+    pub fn add_lines(lines: &[Line], new_lines: Vec<Line>) -> Self {
+        Command::AddLines { index: lines.len(), lines: new_lines }
+    }
+
+    pub fn insert_line(index: usize, line: Line) -> Self {
+        Command::AddLines { index, lines: vec![line] }
+    }
+
+    pub fn delete_line(lines: &[Line], index: usize) -> Self {
+        Command::DeleteLine { index, line: lines[index].clone() }
+    }
+
+    // Lines are edited live, `before` is how the line was.
+    pub fn edit_line(index: usize, before: Line, after: Line) -> Self {
+        Command::EditLine { index, before, after }
+    }
+
+    pub fn move_line(from: usize, to: usize) -> Self {
+        Command::MoveLine { from, to }
+    }
+
+    // Lines on hidden and locked layers are kept.
+    pub fn clear(lines: &[Line], layers: &Layers) -> Self {
+        Command::Clear {
+            lines: lines
+                .iter()
+                .enumerate()
+                .filter(|(_, line)| layers.is_editable(line.layer))
+                .map(|(index, line)| (index, line.clone()))
+                .collect(),
+        }
+    }
+
+    pub fn description(&self) -> String {
+        match self {
+
+            Command::AddLines { index, lines } if lines.len() == 1 => {
+                format!("Add line {}", index)
+            }
+
+            Command::AddLines { lines, .. } => {
+                format!("Add {} lines", lines.len())
+            }
+
+            Command::DeleteLine { index, .. } => {
+                format!("Delete line {}", index)
+            }
+
+            Command::EditLine { index, before, after } => {
+                if before.layer != after.layer {
+                    format!("Move line {} to another layer", index)
+                } else if before.group != after.group {
+                    format!("Move line {} to another group", index)
+                } else if before.start != after.start || before.end != after.end {
+                    format!("Move line {}", index)
+                } else {
+                    format!("Change style of line {}", index)
+                }
+            }
+
+            Command::MoveLine { from, to } => {
+                format!("Reorder line {} to {}", from, to)
+            }
+
+            Command::Clear { lines } => {
+                format!("Clear {} lines", lines.len())
+            }
+
+            Command::AddLayer { layer, .. } => {
+                format!("Add layer {}", layer.name)
+            }
+
+            Command::RemoveLayer { layer, .. } => {
+                format!("Remove layer {}", layer.name)
+            }
+
+            Command::AddGroup { group, .. } => {
+                format!("Add group {}", group.name)
+            }
+
+            Command::RemoveGroup { group, .. } => {
+                format!("Remove group {}", group.name)
+            }
+
+            Command::EditGroup { before, after } => {
+                if before.parent != after.parent {
+                    format!("Move group {} to another parent", after.name)
+                } else if before.name != after.name {
+                    format!("Rename group {} to {}", before.name, after.name)
+                } else {
+                    format!("Transform group {}", after.name)
+                }
+            }
+
+            Command::Batch { commands } => {
+                commands.iter().map(Command::description).collect::<Vec<_>>().join(", ")
+            }
+        }
+    }
+
+    // Where the line at `index` is once the command is applied, None if it
+    // is gone.
+    pub fn map_index(&self, index: usize) -> Option<usize> {
+        match self {
+
+            Command::AddLines { index: at, lines } => {
+                Some(if index >= *at { index + lines.len() } else { index })
+            }
+
+            Command::DeleteLine { index: at, .. } => {
+                match index.cmp(at) {
+                    std::cmp::Ordering::Less => Some(index),
+                    std::cmp::Ordering::Equal => None,
+                    std::cmp::Ordering::Greater => Some(index - 1),
+                }
+            }
+
+            Command::EditLine { .. } => Some(index),
+
+            Command::MoveLine { from, to } => Some(moved_index(index, *from, *to)),
+
+            Command::Clear { lines } => {
+                if lines.iter().any(|(at, _)| *at == index) {
+                    None
+                } else {
+                    Some(index - lines.iter().filter(|(at, _)| *at < index).count())
+                }
+            }
+
+            Command::AddLayer { .. }
+            | Command::RemoveLayer { .. }
+            | Command::AddGroup { .. }
+            | Command::RemoveGroup { .. }
+            | Command::EditGroup { .. } => Some(index),
+
+            Command::Batch { commands } => {
+                commands.iter().try_fold(index, |index, command| command.map_index(index))
+            }
+        }
+    }
+
+    // Where the line at `index` is once the command is reverted.
+    pub fn unmap_index(&self, index: usize) -> Option<usize> {
+        match self {
+
+            Command::AddLines { index: at, lines } => {
+                if index < *at {
+                    Some(index)
+                } else if index < at + lines.len() {
+                    None
+                } else {
+                    Some(index - lines.len())
+                }
+            }
+
+            Command::DeleteLine { index: at, .. } => {
+                Some(if index >= *at { index + 1 } else { index })
+            }
+
+            Command::EditLine { .. } => Some(index),
+
+            Command::MoveLine { from, to } => Some(moved_index(index, *to, *from)),
+
+            Command::Clear { lines } => {
+                let mut index = index;
+                for (at, _) in lines {
+                    if *at <= index {
+                        index += 1;
+                    }
+                }
+                Some(index)
+            }
+
+            Command::AddLayer { .. }
+            | Command::RemoveLayer { .. }
+            | Command::AddGroup { .. }
+            | Command::RemoveGroup { .. }
+            | Command::EditGroup { .. } => Some(index),
+
+            Command::Batch { commands } => {
+                commands.iter().rev().try_fold(index, |index, command| command.unmap_index(index))
+            }
+        }
+    }
+
+    fn apply(&self, lines: &mut Vec<Line>, layers: &mut Layers, graph: &mut SceneGraph) {
+        match self {
+
+            Command::AddLines { index, lines: new_lines } => {
+                lines.splice(*index..*index, new_lines.iter().cloned());
+            }
+
+            Command::DeleteLine { index, .. } => {
+                lines.remove(*index);
+            }
+
+            Command::EditLine { index, after, .. } => {
+                lines[*index] = after.clone();
+            }
+
+            Command::MoveLine { from, to } => {
+                let line = lines.remove(*from);
+                lines.insert(*to, line);
+            }
+
+            Command::Clear { lines: cleared_lines } => {
+                for (index, _) in cleared_lines.iter().rev() {
+                    lines.remove(*index);
+                }
+            }
+
+            Command::AddLayer { position, layer } => {
+                layers.insert(*position, layer.clone());
+            }
+
+            Command::RemoveLayer { layer, .. } => {
+                layers.remove(layer.id);
+            }
+
+            Command::AddGroup { position, group } => {
+                graph.insert(*position, group.clone());
+            }
+
+            Command::RemoveGroup { group, .. } => {
+                graph.remove(group.id);
+            }
+
+            Command::EditGroup { after, .. } => {
+                graph.set_group(after.clone());
+            }
+
+            Command::Batch { commands } => {
+                for command in commands {
+                    command.apply(lines, layers, graph);
+                }
+            }
+        }
+    }
+
+    fn revert(&self, lines: &mut Vec<Line>, layers: &mut Layers, graph: &mut SceneGraph) {
+        match self {
+
+            Command::AddLines { index, lines: new_lines } => {
+                lines.drain(*index..*index + new_lines.len());
+            }
+
+            Command::DeleteLine { index, line } => {
+                lines.insert(*index, line.clone());
+            }
+
+            Command::EditLine { index, before, .. } => {
+                lines[*index] = before.clone();
+            }
+
+            Command::MoveLine { from, to } => {
+                let line = lines.remove(*to);
+                lines.insert(*from, line);
+            }
+
+            Command::Clear { lines: cleared_lines } => {
+                for (index, line) in cleared_lines {
+                    lines.insert(*index, line.clone());
+                }
+            }
+
+            Command::AddLayer { layer, .. } => {
+                layers.remove(layer.id);
+            }
+
+            Command::RemoveLayer { position, layer } => {
+                layers.insert(*position, layer.clone());
+            }
+
+            Command::AddGroup { group, .. } => {
+                graph.remove(group.id);
+            }
+
+            Command::RemoveGroup { position, group } => {
+                graph.insert(*position, group.clone());
+            }
+
+            Command::EditGroup { before, .. } => {
+                graph.set_group(before.clone());
+            }
+
+            Command::Batch { commands } => {
+                for command in commands.iter().rev() {
+                    command.revert(lines, layers, graph);
+                }
+            }
+        }
+    }
+}
+
+// Where the line at `index` is after the one at `from` is moved to `to`.
+fn moved_index(index: usize, from: usize, to: usize) -> usize {
+
+    if index == from {
+        return to;
+    }
+
+    let index = if index > from { index - 1 } else { index };
+    if index >= to { index + 1 } else { index }
+}
+
+pub struct History {
+    undo_stack: VecDeque<Command>,
+    redo_stack: Vec<Command>,
+    max_size: usize,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self {
+            undo_stack: VecDeque::new(),
+            redo_stack: Vec::new(),
+            max_size: DEFAULT_MAX_HISTORY_SIZE,
+        }
+    }
+}
+
+impl History {
+
+    pub fn execute(
+        &mut self,
+        command: Command,
+        lines: &mut Vec<Line>,
+        layers: &mut Layers,
+        graph: &mut SceneGraph,
+    ) {
+
+        command.apply(lines, layers, graph);
+
+        self.undo_stack.push_back(command);
+        self.redo_stack.clear();
+        self.trim();
+    }
+
+    // Returns the command undone.
+    pub fn undo(
+        &mut self,
+        lines: &mut Vec<Line>,
+        layers: &mut Layers,
+        graph: &mut SceneGraph,
+    ) -> Option<&Command> {
+
+        let command = self.undo_stack.pop_back()?;
+        command.revert(lines, layers, graph);
+        self.redo_stack.push(command);
+
+        self.redo_stack.last()
+    }
+
+    // Returns the command redone.
+    pub fn redo(
+        &mut self,
+        lines: &mut Vec<Line>,
+        layers: &mut Layers,
+        graph: &mut SceneGraph,
+    ) -> Option<&Command> {
+
+        let command = self.redo_stack.pop()?;
+        command.apply(lines, layers, graph);
+        self.undo_stack.push_back(command);
+
+        self.undo_stack.back()
+    }
+
+    pub fn get_undo_count(&self) -> usize {
+        self.undo_stack.len()
+    }
+
+    pub fn get_undo_commands(&self) -> impl Iterator<Item = &Command> {
+        self.undo_stack.iter()
+    }
+
+    // Next command to redo first.
+    pub fn get_redo_commands(&self) -> impl Iterator<Item = &Command> {
+        self.redo_stack.iter().rev()
+    }
+
+    pub fn get_max_size(&self) -> usize {
+        self.max_size
+    }
+
+    pub fn set_max_size(&mut self, max_size: usize) {
+        self.max_size = max_size;
+        self.trim();
+    }
+
+    fn trim(&mut self) {
+        while self.undo_stack.len() > self.max_size {
+            self.undo_stack.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm as glm;
+
+    // Told apart by their x.
+    fn line(x: f32, layer: u32) -> Line {
+        let mut line = Line::new(glm::vec2(x, 0.0), glm::vec2(x, 1.0));
+        line.layer = layer;
+        line
+    }
+
+    fn xs(lines: &[Line]) -> Vec<f32> {
+        lines.iter().map(|line| line.start.x).collect()
+    }
+
+    // Every line that is kept is mapped to where it went and back.
+    fn assert_maps_back(command: &Command, count: usize) {
+        for index in 0..count {
+            if let Some(mapped) = command.map_index(index) {
+                assert_eq!(command.unmap_index(mapped), Some(index));
+            }
+        }
+    }
+
+    #[test]
+    fn clear_keeps_hidden_and_locked_lines() {
+        let mut layers = Layers::default();
+        let mut locked = layers.new_layer();
+        locked.locked = true;
+        let mut hidden = layers.new_layer();
+        hidden.visible = false;
+        let (locked_id, hidden_id) = (locked.id, hidden.id);
+        layers.insert(1, locked);
+        layers.insert(2, hidden);
+
+        let mut lines = vec![
+            line(0.0, 0), line(1.0, locked_id), line(2.0, 0), line(3.0, hidden_id), line(4.0, 0),
+        ];
+        let mut graph = SceneGraph::default();
+        let mut history = History::default();
+
+        let command = Command::clear(&lines, &layers);
+        assert_eq!(command.map_index(0), None);
+        assert_eq!(command.map_index(1), Some(0));
+        assert_eq!(command.map_index(3), Some(1));
+        assert_eq!(command.unmap_index(0), Some(1));
+        assert_eq!(command.unmap_index(1), Some(3));
+        assert_maps_back(&command, lines.len());
+
+        history.execute(command, &mut lines, &mut layers, &mut graph);
+        assert_eq!(xs(&lines), [1.0, 3.0]);
+
+        history.undo(&mut lines, &mut layers, &mut graph);
+        assert_eq!(xs(&lines), [0.0, 1.0, 2.0, 3.0, 4.0]);
+    }
+
+    #[test]
+    fn move_line_down_and_up() {
+        let mut lines: Vec<Line> = (0..4).map(|x| line(x as f32, 0)).collect();
+        let mut layers = Layers::default();
+        let mut graph = SceneGraph::default();
+
+        for (from, to, moved) in [
+            (1, 3, [0.0, 2.0, 3.0, 1.0]),
+            (3, 0, [3.0, 0.0, 1.0, 2.0]),
+        ] {
+            let command = Command::move_line(from, to);
+            assert_eq!(command.map_index(from), Some(to));
+            assert_maps_back(&command, lines.len());
+
+            command.apply(&mut lines, &mut layers, &mut graph);
+            assert_eq!(xs(&lines), moved);
+            for index in 0..lines.len() {
+                let mapped = command.map_index(index).unwrap();
+                assert_eq!(lines[mapped].start.x, index as f32);
+            }
+
+            command.revert(&mut lines, &mut layers, &mut graph);
+            assert_eq!(xs(&lines), [0.0, 1.0, 2.0, 3.0]);
+        }
+    }
+
+    #[test]
+    fn add_lines_in_the_middle() {
+        let mut lines = vec![line(0.0, 0), line(1.0, 0), line(2.0, 0)];
+        let mut layers = Layers::default();
+        let mut graph = SceneGraph::default();
+
+        let command = Command::AddLines { index: 1, lines: vec![line(10.0, 0), line(11.0, 0)] };
+        assert_eq!(command.map_index(0), Some(0));
+        assert_eq!(command.map_index(1), Some(3));
+        assert_eq!(command.map_index(2), Some(4));
+        assert_eq!(command.unmap_index(1), None);
+        assert_eq!(command.unmap_index(2), None);
+        assert_eq!(command.unmap_index(3), Some(1));
+        assert_maps_back(&command, lines.len());
+
+        command.apply(&mut lines, &mut layers, &mut graph);
+        assert_eq!(xs(&lines), [0.0, 10.0, 11.0, 1.0, 2.0]);
+
+        command.revert(&mut lines, &mut layers, &mut graph);
+        assert_eq!(xs(&lines), [0.0, 1.0, 2.0]);
+    }
+
+    #[test]
+    fn redo_after_trimming() {
+        let mut lines = Vec::new();
+        let mut layers = Layers::default();
+        let mut graph = SceneGraph::default();
+        let mut history = History::default();
+        history.set_max_size(2);
+
+        for x in 0..3 {
+            let command = Command::add_line(&lines, line(x as f32, 0));
+            history.execute(command, &mut lines, &mut layers, &mut graph);
+        }
+        assert_eq!(history.get_undo_count(), 2);
+
+        // The first line can no longer be undone.
+        assert!(history.undo(&mut lines, &mut layers, &mut graph).is_some());
+        assert!(history.undo(&mut lines, &mut layers, &mut graph).is_some());
+        assert!(history.undo(&mut lines, &mut layers, &mut graph).is_none());
+        assert_eq!(xs(&lines), [0.0]);
+
+        assert!(history.redo(&mut lines, &mut layers, &mut graph).is_some());
+        assert!(history.redo(&mut lines, &mut layers, &mut graph).is_some());
+        assert!(history.redo(&mut lines, &mut layers, &mut graph).is_none());
+        assert_eq!(xs(&lines), [0.0, 1.0, 2.0]);
+        assert_eq!(history.get_undo_count(), 2);
+    }
+}
@@ -0,0 +1,113 @@
+use nalgebra_glm as glm;
+
+use crate::{
+    common::ColorU8,
+    line::Line,
+    lines_renderer::{LinesRenderer, LineAlgorithem, Stroke},
+    layers::Layers,
+};
+
+pub struct LineHit {
+    pub line: usize,
+    // From the pixel center to the ideal segment.
+    pub distance: f32,
+    // Every step of the trace that painted the pixel, across the width of
+    // the line and outside its stipple gaps.
+    pub steps: Vec<usize>,
+    // The one of its layer, which may not be the view's.
+    pub algorithem: LineAlgorithem,
+}
+
+pub struct PixelInspection {
+    // In canvas coordinates, the pixel center.
+    pub pixel: glm::I32Vec2,
+    pub color: ColorU8,
+    pub algorithem: LineAlgorithem,
+    pub hits: Vec<LineHit>,
+}
+
+pub struct PixelInspector {
+    pub show_tooltip: bool,
+    inspection: Option<PixelInspection>,
+}
+
+impl Default for PixelInspector {
+    fn default() -> Self {
+        Self {
+            show_tooltip: true,
+            inspection: None,
+        }
+    }
+}
+
+impl PixelInspector {
+
+    pub fn get_inspection(&self) -> Option<&PixelInspection> {
+        self.inspection.as_ref()
+    }
+
+    pub fn clear(&mut self) {
+        self.inspection = None;
+    }
+
+    // Inspects the pixel of `canvas` under `canvas_pos`, after it was
+    // rendered with `algorithem` and the algorithms of the layers.
+    pub fn update(
+        &mut self,
+        lines_renderer: &LinesRenderer,
+        canvas: usize,
+        algorithem: LineAlgorithem,
+        lines: &[Line],
+        layers: &Layers,
+        canvas_pos: &glm::Vec2,
+    ) {
+
+        let (tex_width, tex_height) = lines_renderer.get_canvas_size();
+        let half_size = glm::vec2((tex_width / 2) as f32, (tex_height / 2) as f32);
+
+        // Texel i is centered on canvas coordinate i - size / 2.
+        let texel = (canvas_pos + half_size).map(|x| x.round());
+        if texel.x < 0.0 || texel.y < 0.0
+            || texel.x >= tex_width as f32 || texel.y >= tex_height as f32 {
+            self.inspection = None;
+            return;
+        }
+        let texel = glm::U16Vec2::new(texel.x as u16, texel.y as u16);
+        let center = glm::vec2(texel.x as f32, texel.y as f32) - half_size;
+
+        let stipple_offsets = LinesRenderer::drawn_stipple_offsets(lines, layers);
+
+        let hits = lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| layers.is_visible(line.layer))
+            .filter_map(|(i, line)| {
+                let algorithem = layers.get_algorithem(line.layer, algorithem);
+                let stroke = Stroke::new(line, &line.style, stipple_offsets[i], tex_width, tex_height);
+                let steps: Vec<usize> = LinesRenderer::trace(&algorithem, line, tex_width, tex_height)
+                    .iter()
+                    .enumerate()
+                    // Like the pixels drawn, the steps off the canvas paint nothing.
+                    .filter(|(_, step)| step.pixel.x < tex_width && step.pixel.y < tex_height)
+                    .filter(|(_, step)| {
+                        let mut painted = false;
+                        stroke.for_each_pixel(&step.pixel, |pixel, _| painted |= pixel == texel);
+                        painted
+                    })
+                    .map(|(step, _)| step)
+                    .collect();
+                if steps.is_empty() {
+                    return None;
+                }
+                Some(LineHit { line: i, distance: line.distance_to(&center), steps, algorithem })
+            })
+            .collect();
+
+        self.inspection = Some(PixelInspection {
+            pixel: glm::I32Vec2::new(center.x as i32, center.y as i32),
+            color: lines_renderer.read_canvas_pixel(canvas, &texel),
+            algorithem,
+            hits,
+        });
+    }
+}
@@ -0,0 +1,141 @@
+use crate::lines_renderer::LineAlgorithem;
+
+#[derive(Clone)]
+pub struct Layer {
+    pub id: u32,
+    pub name: String,
+    pub visible: bool,
+    pub locked: bool,
+    pub opacity: f32,
+    // Draws the layer with this algorithm in every view instead of the
+    // view's own.
+    pub algorithem: Option<LineAlgorithem>,
+}
+
+impl Layer {
+    fn new(id: u32, name: String) -> Self {
+        Self {
+            id,
+            name,
+            visible: true,
+            locked: false,
+            opacity: 1.0,
+            algorithem: None,
+        }
+    }
+}
+
+// Layers are drawn in order, the first one at the bottom. Lines refer to
+// their layer by id, so reordering the layers does not touch them.
+pub struct Layers {
+    layers: Vec<Layer>,
+    active: u32,
+    next_id: u32,
+}
+
+impl Default for Layers {
+    fn default() -> Self {
+        Self {
+            layers: vec![Layer::new(0, "Layer 0".to_string())],
+            active: 0,
+            next_id: 1,
+        }
+    }
+}
+
+impl Layers {
+
+    pub fn get_layers(&self) -> &[Layer] {
+        &self.layers
+    }
+
+    pub fn get_layers_mut(&mut self) -> &mut [Layer] {
+        &mut self.layers
+    }
+
+    // The layer new lines go to.
+    pub fn get_active(&self) -> u32 {
+        self.active
+    }
+
+    pub fn set_active(&mut self, id: u32) {
+        if self.layers.iter().any(|layer| layer.id == id) {
+            self.active = id;
+        }
+    }
+
+    // Lines only refer to layers that exist, adding and removing layers
+    // goes through the history like the lines do. None still keeps a lost
+    // id from landing on another layer.
+    pub fn get_index(&self, id: u32) -> Option<usize> {
+        self.layers.iter().position(|layer| layer.id == id)
+    }
+
+    pub fn get(&self, id: u32) -> Option<&Layer> {
+        self.layers.iter().find(|layer| layer.id == id)
+    }
+
+    pub fn is_visible(&self, id: u32) -> bool {
+        self.get(id).is_some_and(|layer| layer.visible)
+    }
+
+    // Hidden and locked lines can not be selected or edited.
+    pub fn is_editable(&self, id: u32) -> bool {
+        self.get(id).is_some_and(|layer| layer.visible && !layer.locked)
+    }
+
+    pub fn get_algorithem(&self, id: u32, view_algorithem: LineAlgorithem) -> LineAlgorithem {
+        self.get(id).and_then(|layer| layer.algorithem).unwrap_or(view_algorithem)
+    }
+
+    // A layer with a new id, not added yet. Ids are never reused, so a layer
+    // brought back by a redo keeps its own.
+    pub fn new_layer(&mut self) -> Layer {
+
+        let id = self.next_id;
+        self.next_id += 1;
+
+        Layer::new(id, format!("Layer {}", id))
+    }
+
+    pub fn find(&self, name: &str) -> Option<u32> {
+        self.layers.iter().find(|layer| layer.name == name).map(|layer| layer.id)
+    }
+
+    // `position` counts from the bottom.
+    pub fn insert(&mut self, position: usize, layer: Layer) {
+        let position = position.min(self.layers.len());
+        self.layers.insert(position, layer);
+    }
+
+    // Returns where the layer was and the layer. The last layer is never
+    // removed.
+    pub fn remove(&mut self, id: u32) -> Option<(usize, Layer)> {
+
+        if self.layers.len() == 1 {
+            return None;
+        }
+
+        let position = self.get_index(id)?;
+        let layer = self.layers.remove(position);
+
+        if self.active == id {
+            self.active = self.layers.last().unwrap().id;
+        }
+
+        Some((position, layer))
+    }
+
+    // Moves the layer at `index` one place up, towards the top.
+    pub fn raise(&mut self, index: usize) {
+        if index + 1 < self.layers.len() {
+            self.layers.swap(index, index + 1);
+        }
+    }
+
+    pub fn lower(&mut self, index: usize) {
+        if index > 0 {
+            self.layers.swap(index, index - 1);
+        }
+    }
+}
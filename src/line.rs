@@ -1,9 +1,220 @@
 
 use nalgebra_glm as glm;
 
+use crate::{
+    common::ColorU8,
+    compositing::{CompositeOp, BlendMode, RasterOp},
+};
+
+pub const MAX_LINE_WIDTH: u16 = 16;
+// The GPU path gets the runs in a fixed size uniform array.
+pub const MAX_STIPPLE_RUNS: usize = 32;
+
+// Which pixels of a line are drawn, counted along its major axis, one per
+// step of the rasterizer.
+#[derive(Clone, PartialEq)]
+pub enum Stipple {
+    Solid,
+    // Like glLineStipple, bit i of `pattern` is step i, repeated `factor`
+    // times, lowest bit first.
+    Mask { pattern: u16, factor: u16 },
+    // Drawn and skipped lengths in pixels, alternating, starting `phase`
+    // pixels into the pattern. Odd lists are repeated like SVG dash arrays.
+    Dashes { lengths: Vec<u16>, phase: u16 },
+}
+
+impl Stipple {
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Stipple::Solid => "Solid",
+            Stipple::Mask { .. } => "Bit mask",
+            Stipple::Dashes { .. } => "Dashes",
+        }
+    }
+
+    // Alternating drawn and skipped lengths, starting with a drawn one.
+    // Empty for solid lines.
+    pub fn runs(&self) -> Vec<u32> {
+
+        let mut runs: Vec<u32> = match self {
+
+            Stipple::Solid => Vec::new(),
+
+            Stipple::Mask { pattern, factor } => {
+                let mut runs = vec![0];
+                for bit in 0..16 {
+                    let on = pattern & (1 << bit) != 0;
+                    if on != (runs.len() % 2 == 1) {
+                        runs.push(0);
+                    }
+                    *runs.last_mut().unwrap() += (*factor).max(1) as u32;
+                }
+                runs
+            }
+
+            Stipple::Dashes { lengths, .. } => {
+                let lengths = lengths.iter().map(|length| *length as u32);
+                if lengths.len() % 2 == 1 {
+                    lengths.clone().chain(lengths).collect()
+                } else {
+                    lengths.collect()
+                }
+            }
+        };
+
+        runs.truncate(MAX_STIPPLE_RUNS);
+
+        runs
+    }
+
+    pub fn get_phase(&self) -> u32 {
+        match self {
+            Stipple::Dashes { phase, .. } => *phase as u32,
+            _ => 0,
+        }
+    }
+}
+
+// `step` counts pixels from the start of the pattern. Patterns with no
+// length draw everything.
+pub fn is_stipple_on(runs: &[u32], step: u32) -> bool {
+
+    let period: u32 = runs.iter().sum();
+    if period == 0 {
+        return true;
+    }
+
+    let mut pos = step % period;
+    let mut on = true;
+
+    for run in runs {
+        if pos < *run {
+            return on;
+        }
+        pos -= run;
+        on = !on;
+    }
+
+    on
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ColorSpace {
+    Srgb,
+    Linear,
+}
+
+impl ColorSpace {
+
+    pub const ALL: [ColorSpace; 2] = [ColorSpace::Srgb, ColorSpace::Linear];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ColorSpace::Srgb => "sRGB",
+            ColorSpace::Linear => "Linear light",
+        }
+    }
+}
+
+// What goes from 0 at the start of a gradient to 1 at its end.
+#[derive(Clone, Copy, PartialEq)]
+pub enum GradientParameter {
+    // The rasterizer steps along the major axis, between the rounded
+    // endpoints.
+    Steps,
+    // The pixel center projected on the ideal segment.
+    Distance,
+}
+
+impl GradientParameter {
+
+    pub const ALL: [GradientParameter; 2] = [GradientParameter::Steps, GradientParameter::Distance];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            GradientParameter::Steps => "By step count",
+            GradientParameter::Distance => "By distance",
+        }
+    }
+}
+
+// Goes from the style color at the start of the line to `end_color`.
+#[derive(Clone, PartialEq)]
+pub struct Gradient {
+    pub end_color: ColorU8,
+    pub space: ColorSpace,
+    pub parameter: GradientParameter,
+}
+
+impl Default for Gradient {
+    fn default() -> Self {
+        Self {
+            end_color: ColorU8 { r: 255, g: 0, b: 0, a: 255 },
+            space: ColorSpace::Srgb,
+            parameter: GradientParameter::Steps,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct LineStyle {
+    pub color: ColorU8,
+    pub width: u16,
+    pub stipple: Stipple,
+    pub gradient: Option<Gradient>,
+    // How the pixels are composited with the ones already drawn. Only the
+    // CPU algorithms use them, the fragment shader draws source over.
+    pub operator: CompositeOp,
+    pub blend_mode: BlendMode,
+    // Replaces the compositing, also only on the CPU.
+    pub raster_op: Option<RasterOp>,
+}
+
+impl Default for LineStyle {
+    fn default() -> Self {
+        Self {
+            color: ColorU8 { r: 255, g: 255, b: 255, a: 255 },
+            width: 1,
+            stipple: Stipple::Solid,
+            gradient: None,
+            operator: CompositeOp::SrcOver,
+            blend_mode: BlendMode::Normal,
+            raster_op: None,
+        }
+    }
+}
+
+impl LineStyle {
+
+    // Pixel offsets across the line, the extra pixel of even widths goes
+    // to the positive side.
+    pub fn width_offsets(&self) -> std::ops::RangeInclusive<i32> {
+        let width = self.width.max(1) as i32;
+        -((width - 1) / 2)..=width / 2
+    }
+
+    pub fn color_at(&self, t: f32) -> ColorU8 {
+        match &self.gradient {
+            None => self.color.clone(),
+            Some(gradient) => match gradient.space {
+                ColorSpace::Srgb => self.color.lerp(&gradient.end_color, t),
+                ColorSpace::Linear => self.color.lerp_linear(&gradient.end_color, t),
+            },
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
 pub struct Line {
     pub start: glm::Vec2,
     pub end: glm::Vec2,
+    pub style: LineStyle,
+    // The id of its layer.
+    pub layer: u32,
+    // The id of its group in the scene graph, the coordinates are in its
+    // space.
+    pub group: Option<u32>,
 }
 
 impl Line {
@@ -12,6 +223,35 @@ impl Line {
         Self {
             start,
             end,
+            style: LineStyle::default(),
+            layer: 0,
+            group: None,
         }
     }
+
+    pub fn with_style(mut self, style: LineStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    // Where `point` projects on the segment, from 0 at the start to 1 at
+    // the end.
+    pub fn project(&self, point: &glm::Vec2) -> f32 {
+
+        let direction = self.end - self.start;
+        let length2 = glm::dot(&direction, &direction);
+
+        if length2 < f32::EPSILON {
+            return 0.0;
+        }
+
+        (glm::dot(&(point - self.start), &direction) / length2).clamp(0.0, 1.0)
+    }
+
+    pub fn distance_to(&self, point: &glm::Vec2) -> f32 {
+
+        let direction = self.end - self.start;
+
+        glm::distance(point, &(self.start + direction * self.project(point)))
+    }
 }
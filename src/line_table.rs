@@ -0,0 +1,619 @@
+use nalgebra_glm as glm;
+use egui_glfw_gl::egui;
+
+use crate::{
+    common::ColorU8,
+    line::{Line, LineStyle, Stipple, Gradient, ColorSpace, GradientParameter, MAX_LINE_WIDTH, MAX_STIPPLE_RUNS},
+    scene::Scene,
+    history::Command,
+    lines_renderer::LineAlgorithem,
+    measure::{measure_line, LineMeasurements},
+    compositing::{CompositeOp, BlendMode, RasterOp},
+};
+
+#[derive(PartialEq, Clone, Copy)]
+pub enum SortKey {
+    Index,
+    StartX,
+    StartY,
+    EndX,
+    EndY,
+    Length,
+    Angle,
+}
+
+impl SortKey {
+
+    const ALL: [SortKey; 7] = [
+        SortKey::Index,
+        SortKey::StartX,
+        SortKey::StartY,
+        SortKey::EndX,
+        SortKey::EndY,
+        SortKey::Length,
+        SortKey::Angle,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            SortKey::Index => "Index",
+            SortKey::StartX => "Start x",
+            SortKey::StartY => "Start y",
+            SortKey::EndX => "End x",
+            SortKey::EndY => "End y",
+            SortKey::Length => "Length",
+            SortKey::Angle => "Angle",
+        }
+    }
+
+    fn value(&self, index: usize, line: &Line) -> f32 {
+        let d = line.end - line.start;
+        match self {
+            SortKey::Index => index as f32,
+            SortKey::StartX => line.start.x,
+            SortKey::StartY => line.start.y,
+            SortKey::EndX => line.end.x,
+            SortKey::EndY => line.end.y,
+            SortKey::Length => glm::length(&d),
+            SortKey::Angle => d.y.atan2(d.x),
+        }
+    }
+}
+
+// The measurements of a row, for the line where it is drawn.
+struct CachedMeasurements {
+    start: glm::Vec2,
+    end: glm::Vec2,
+    canvas_size: (u16, u16),
+    measurements: LineMeasurements,
+}
+
+enum RowAction {
+    MoveUp(usize),
+    MoveDown(usize),
+    Duplicate(usize),
+    Delete(usize),
+}
+
+pub struct LineTable {
+    new_line: [f32; 4],
+    sort_key: SortKey,
+    descending: bool,
+    filter: String,
+    show_measurements: bool,
+    // Rasterizing every row with every algorithm each frame is slow, rows
+    // are measured again once their line or the canvas changes.
+    measurements: Vec<Option<CachedMeasurements>>,
+    pending_edit: Option<(usize, Line)>,
+}
+
+impl Default for LineTable {
+    fn default() -> Self {
+        Self {
+            new_line: [0.0; 4],
+            sort_key: SortKey::Index,
+            descending: false,
+            filter: String::new(),
+            show_measurements: false,
+            measurements: Vec::new(),
+            pending_edit: None,
+        }
+    }
+}
+
+impl LineTable {
+
+    // Returns the row clicked to select its line.
+    // `new_line_style` is the style of the lines added from here and drawn
+    // on the canvas.
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        scene: &mut Scene,
+        canvas_size: (u16, u16),
+        new_line_style: &mut LineStyle,
+    ) -> Option<usize> {
+
+        ui.horizontal(|ui| {
+            ui.label("New:");
+            for value in &mut self.new_line {
+                ui.add(egui::DragValue::new(value).speed(0.5));
+            }
+            let can_add = scene.layers.is_editable(scene.layers.get_active());
+            if ui.add(egui::Button::new("Add").enabled(can_add)).clicked() {
+                let [xi, yi, xf, yf] = self.new_line;
+                scene.add_line(
+                    Line::new(glm::vec2(xi, yi), glm::vec2(xf, yf)).with_style(new_line_style.clone())
+                );
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("New style:");
+            edit_line_style(ui, "new_line_stipple", new_line_style);
+        });
+
+        let mut active_row = None;
+
+        if let Some(i) = scene.selection.get_selected() {
+            ui.horizontal(|ui| {
+                ui.label(format!("Line {} style:", i));
+                let mut style = scene.lines[i].style.clone();
+                let (changed, active) = edit_line_style(ui, "selected_line_stipple", &mut style);
+                // The color picker reports changes only on the frames the
+                // color moves, so the edit lasts while the pointer is down.
+                let held = ui.input().pointer.any_down()
+                    && matches!(self.pending_edit, Some((pending, _)) if pending == i);
+                if active || held {
+                    active_row = Some(i);
+                }
+                if changed {
+                    let mut line = scene.lines[i].clone();
+                    line.style = style;
+                    self.edit_line(scene, i, line);
+                }
+            });
+        }
+
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_label("Sort")
+                .selected_text(self.sort_key.name())
+                .show_ui(ui, |ui| {
+                    for key in SortKey::ALL {
+                        ui.selectable_value(&mut self.sort_key, key, key.name());
+                    }
+                });
+            ui.checkbox(&mut self.descending, "Descending");
+        });
+
+        ui.checkbox(&mut self.show_measurements, "Measurements");
+
+        ui.horizontal(|ui| {
+            ui.label("Filter:");
+            ui.text_edit_singleline(&mut self.filter);
+        });
+
+        let rows = self.visible_rows(&scene.lines);
+        self.measurements.truncate(scene.lines.len());
+        let can_reorder = self.sort_key == SortKey::Index && !self.descending;
+
+        let mut clicked_row = None;
+        let mut action = None;
+
+        egui::ScrollArea::from_max_height(250.0).show(ui, |ui| {
+            egui::Grid::new("line_table").striped(true).show(ui, |ui| {
+
+                ui.label("#");
+                ui.label("Layer");
+                ui.label("Group").on_hover_text("The coordinates are in the space of the group");
+                ui.label("Start x");
+                ui.label("Start y");
+                ui.label("End x");
+                ui.label("End y");
+                if self.show_measurements {
+                    ui.label("Length");
+                    ui.label("Angle");
+                    ui.label("m").on_hover_text(
+                        "y = m x + b for moderate lines, x = m y + b for steep ones"
+                    );
+                    ui.label("b");
+                    ui.label("Octant");
+                    ui.label("Kind");
+                    for algorithem in LineAlgorithem::ALL {
+                        ui.label(algorithem.short_name())
+                            .on_hover_text(format!("Pixels drawn by {}", algorithem.name()));
+                    }
+                }
+                ui.end_row();
+
+                for &i in &rows {
+
+                    // Lines on hidden or locked layers are only listed.
+                    let editable = scene.layers.is_editable(scene.lines[i].layer);
+
+                    let selected = scene.selection.get_selected() == Some(i);
+                    if ui.selectable_label(selected, i.to_string()).clicked() && editable {
+                        clicked_row = Some(i);
+                    }
+
+                    let line = &scene.lines[i];
+                    let layer_name = scene.layers.get(line.layer).map_or("-", |layer| layer.name.as_str());
+                    let mut layer = line.layer;
+
+                    if editable {
+                        egui::ComboBox::from_id_source(("line_layer", i))
+                            .selected_text(layer_name)
+                            .show_ui(ui, |ui| {
+                                for option in scene.layers.get_layers() {
+                                    ui.selectable_value(&mut layer, option.id, &option.name);
+                                }
+                            });
+                    } else {
+                        ui.label(layer_name);
+                    }
+
+                    let group_name = |group: Option<u32>| {
+                        match group.and_then(|group| scene.graph.get_group(group)) {
+                            Some(group) => group.name.clone(),
+                            None => "-".to_string(),
+                        }
+                    };
+                    let mut group = line.group;
+
+                    if editable {
+                        egui::ComboBox::from_id_source(("line_group", i))
+                            .selected_text(group_name(group))
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(&mut group, None, "-");
+                                for option in scene.graph.get_groups() {
+                                    ui.selectable_value(&mut group, Some(option.id), &option.name);
+                                }
+                            });
+                    } else {
+                        ui.label(group_name(group));
+                    }
+
+                    let mut coords = [line.start.x, line.start.y, line.end.x, line.end.y];
+                    let mut changed = layer != line.layer;
+
+                    for value in &mut coords {
+                        if !editable {
+                            ui.label(format!("{:.2}", value));
+                            continue;
+                        }
+                        let response = ui.add(
+                            egui::DragValue::new(value).speed(0.5).max_decimals(2)
+                        );
+                        changed |= response.changed();
+                        if response.dragged() || response.has_focus() {
+                            active_row = Some(i);
+                        }
+                    }
+
+                    if self.show_measurements {
+                        let world_line = scene.graph.to_world(&scene.lines[i]);
+                        let measurements = self.get_measurements(i, &world_line, canvas_size);
+                        ui.label(format!("{:.2}", measurements.length));
+                        ui.label(format!("{:.2}°", measurements.angle));
+                        ui.label(format_finite(measurements.m));
+                        ui.label(format_finite(measurements.b));
+                        ui.label(measurements.octant.to_string());
+                        ui.label(measurements.kind.name());
+                        for count in measurements.pixel_counts {
+                            ui.label(count.to_string());
+                        }
+                    }
+
+                    if changed {
+                        let mut line = scene.lines[i].clone();
+                        line.start = glm::vec2(coords[0], coords[1]);
+                        line.end = glm::vec2(coords[2], coords[3]);
+                        line.layer = layer;
+                        self.edit_line(scene, i, line);
+                    }
+
+                    // Moved lines stay where they are drawn.
+                    if group != scene.lines[i].group {
+                        let line = scene.graph.move_to_group(&scene.lines[i], group);
+                        self.edit_line(scene, i, line);
+                    }
+
+                    ui.horizontal(|ui| {
+                        let up = egui::Button::new("^").small().enabled(editable && can_reorder && i > 0);
+                        if ui.add(up).clicked() {
+                            action = Some(RowAction::MoveUp(i));
+                        }
+                        let down = egui::Button::new("v")
+                            .small()
+                            .enabled(editable && can_reorder && i + 1 < scene.lines.len());
+                        if ui.add(down).clicked() {
+                            action = Some(RowAction::MoveDown(i));
+                        }
+                        if ui.add(egui::Button::new("dup").small().enabled(editable)).clicked() {
+                            action = Some(RowAction::Duplicate(i));
+                        }
+                        if ui.add(egui::Button::new("x").small().enabled(editable)).clicked() {
+                            action = Some(RowAction::Delete(i));
+                        }
+                    });
+
+                    ui.end_row();
+                }
+            });
+        });
+
+        if action.is_some() || !matches!(self.pending_edit, Some((i, _)) if active_row == Some(i)) {
+            self.commit_edit(scene);
+        }
+
+        match action {
+            Some(RowAction::MoveUp(i)) => scene.execute(Command::move_line(i, i - 1)),
+            Some(RowAction::MoveDown(i)) => scene.execute(Command::move_line(i, i + 1)),
+            Some(RowAction::Duplicate(i)) => {
+                scene.execute(Command::insert_line(i + 1, scene.lines[i].clone()));
+            }
+            Some(RowAction::Delete(i)) => scene.delete_line(i),
+            None => {}
+        }
+
+        clicked_row
+    }
+
+    fn get_measurements(
+        &mut self,
+        row: usize,
+        world_line: &Line,
+        canvas_size: (u16, u16),
+    ) -> &LineMeasurements {
+
+        if self.measurements.len() <= row {
+            self.measurements.resize_with(row + 1, || None);
+        }
+
+        let cached = &mut self.measurements[row];
+        let is_valid = matches!(
+            cached,
+            Some(cached) if cached.start == world_line.start
+                && cached.end == world_line.end
+                && cached.canvas_size == canvas_size
+        );
+
+        if !is_valid {
+            let (width, height) = canvas_size;
+            *cached = Some(CachedMeasurements {
+                start: world_line.start,
+                end: world_line.end,
+                canvas_size,
+                measurements: measure_line(world_line, width, height),
+            });
+        }
+
+        &cached.as_ref().unwrap().measurements
+    }
+
+    fn visible_rows(&self, lines: &[Line]) -> Vec<usize> {
+
+        let filter = self.filter.to_lowercase();
+
+        let mut rows: Vec<usize> = lines
+            .iter()
+            .enumerate()
+            .filter(|(i, line)| filter.is_empty() || row_text(*i, line).contains(&filter))
+            .map(|(i, _)| i)
+            .collect();
+
+        rows.sort_by(|&a, &b| {
+            let a = self.sort_key.value(a, &lines[a]);
+            let b = self.sort_key.value(b, &lines[b]);
+            a.total_cmp(&b)
+        });
+
+        if self.descending {
+            rows.reverse();
+        }
+
+        rows
+    }
+
+    // Edits are applied live while a value is being dragged or typed and
+    // recorded as a single command once the row is left.
+    fn edit_line(&mut self, scene: &mut Scene, index: usize, line: Line) {
+
+        if !matches!(self.pending_edit, Some((i, _)) if i == index) {
+            self.commit_edit(scene);
+            self.pending_edit = Some((index, scene.lines[index].clone()));
+        }
+
+        scene.lines[index] = line;
+    }
+
+    // Has to be called before anything else changes the lines, undoing
+    // included, so the edit is recorded in the state it was made in.
+    pub fn commit_edit(&mut self, scene: &mut Scene) {
+
+        if let Some((index, before)) = self.pending_edit.take() {
+
+            let after = match scene.lines.get(index) {
+                Some(line) => line.clone(),
+                None => return,
+            };
+
+            if before != after {
+                scene.execute(Command::edit_line(index, before, after));
+            }
+        }
+    }
+}
+
+// Returns whether the style changed and whether it is still being edited,
+// so the edits can be recorded as a single command.
+// `id_source` tells apart the combo boxes of different editors.
+pub fn edit_line_style(ui: &mut egui::Ui, id_source: &str, style: &mut LineStyle) -> (bool, bool) {
+
+    let before = style.clone();
+    // The widgets that keep an edit going while they are dragged.
+    let mut responses = Vec::new();
+
+    ui.vertical(|ui| {
+
+        ui.horizontal(|ui| {
+
+            responses.push(edit_color(ui, &mut style.color));
+
+            responses.push(ui.add(
+                egui::DragValue::new(&mut style.width)
+                    .speed(0.1)
+                    .clamp_range(1..=MAX_LINE_WIDTH)
+                    .suffix(" px wide")
+            ));
+
+            let stipple = &mut style.stipple;
+            egui::ComboBox::from_id_source(id_source)
+                .selected_text(stipple.name())
+                .show_ui(ui, |ui| {
+                    for option in [
+                        Stipple::Solid,
+                        Stipple::Mask { pattern: 0x0F0F, factor: 1 },
+                        Stipple::Dashes { lengths: vec![6, 3], phase: 0 },
+                    ] {
+                        let selected = stipple.name() == option.name();
+                        if ui.selectable_label(selected, option.name()).clicked() && !selected {
+                            *stipple = option;
+                        }
+                    }
+                });
+        });
+
+        match &mut style.stipple {
+
+            Stipple::Solid => {}
+
+            Stipple::Mask { pattern, factor } => {
+                ui.horizontal(|ui| {
+                    ui.spacing_mut().item_spacing.x = 2.0;
+                    for bit in 0..16 {
+                        let on = *pattern & (1 << bit) != 0;
+                        if ui.selectable_label(on, if on { "1" } else { "0" }).clicked() {
+                            *pattern ^= 1 << bit;
+                        }
+                    }
+                    ui.spacing_mut().item_spacing.x = 8.0;
+                    responses.push(ui.add(
+                        egui::DragValue::new(factor).speed(0.1).clamp_range(1..=16).prefix("x")
+                    ));
+                });
+            }
+
+            Stipple::Dashes { lengths, phase } => {
+                ui.horizontal(|ui| {
+                    for length in lengths.iter_mut() {
+                        responses.push(ui.add(
+                            egui::DragValue::new(length).speed(0.1).clamp_range(0..=64)
+                        ));
+                    }
+                    let can_add = lengths.len() < MAX_STIPPLE_RUNS;
+                    if ui.add(egui::Button::new("+").enabled(can_add)).clicked() {
+                        lengths.push(lengths.last().copied().unwrap_or(4));
+                    }
+                    if ui.add(egui::Button::new("-").enabled(lengths.len() > 1)).clicked() {
+                        lengths.pop();
+                    }
+                    responses.push(ui.add(
+                        egui::DragValue::new(phase).speed(0.1).clamp_range(0..=255).prefix("phase ")
+                    ));
+                });
+            }
+        }
+
+        ui.horizontal(|ui| {
+
+            let mut has_gradient = style.gradient.is_some();
+            if ui.checkbox(&mut has_gradient, "Gradient to").changed() {
+                style.gradient = if has_gradient { Some(Gradient::default()) } else { None };
+            }
+
+            if let Some(gradient) = &mut style.gradient {
+
+                responses.push(edit_color(ui, &mut gradient.end_color));
+
+                let space = &mut gradient.space;
+                egui::ComboBox::from_id_source(format!("{}_space", id_source))
+                    .selected_text(space.name())
+                    .show_ui(ui, |ui| {
+                        for option in ColorSpace::ALL {
+                            ui.selectable_value(space, option, option.name());
+                        }
+                    });
+
+                let parameter = &mut gradient.parameter;
+                egui::ComboBox::from_id_source(format!("{}_parameter", id_source))
+                    .selected_text(parameter.name())
+                    .show_ui(ui, |ui| {
+                        for option in GradientParameter::ALL {
+                            ui.selectable_value(parameter, option, option.name());
+                        }
+                    });
+            }
+        });
+
+        ui.horizontal(|ui| {
+
+            let operator = &mut style.operator;
+            egui::ComboBox::from_id_source(format!("{}_operator", id_source))
+                .selected_text(operator.name())
+                .show_ui(ui, |ui| {
+                    for option in CompositeOp::ALL {
+                        ui.selectable_value(operator, option, option.name());
+                    }
+                });
+
+            let blend_mode = &mut style.blend_mode;
+            egui::ComboBox::from_id_source(format!("{}_blend_mode", id_source))
+                .selected_text(blend_mode.name())
+                .show_ui(ui, |ui| {
+                    for option in BlendMode::ALL {
+                        ui.selectable_value(blend_mode, option, option.name());
+                    }
+                });
+
+            edit_raster_op(ui, format!("{}_raster_op", id_source), &mut style.raster_op);
+        })
+        .response
+        .on_hover_text(
+            "Compositing operator and blend mode, used by the CPU algorithms. \
+             A raster operation replaces them."
+        );
+    });
+
+    let changed = *style != before;
+    let active = responses
+        .iter()
+        .any(|response| response.changed() || response.dragged() || response.has_focus());
+
+    (changed, active)
+}
+
+fn edit_color(ui: &mut egui::Ui, color: &mut ColorU8) -> egui::Response {
+
+    let mut hsva = egui::color::Hsva::from_srgba_unmultiplied([color.r, color.g, color.b, color.a]);
+    let response = egui::widgets::color_picker::color_edit_button_hsva(
+        ui, &mut hsva, egui::widgets::color_picker::Alpha::OnlyBlend
+    );
+    if response.changed() {
+        let [r, g, b, a] = hsva.to_srgba_unmultiplied();
+        *color = ColorU8 { r, g, b, a };
+    }
+
+    response
+}
+
+// Zero length lines have no slope.
+fn format_finite(value: f32) -> String {
+    if value.is_finite() { format!("{:.3}", value) } else { "-".to_string() }
+}
+
+fn row_text(index: usize, line: &Line) -> String {
+    format!(
+        "{} ({}, {}) -> ({}, {})",
+        index,
+        line.start.x as i32,
+        line.start.y as i32,
+        line.end.x as i32,
+        line.end.y as i32,
+    )
+}
+
+// None composites the line instead.
+pub fn edit_raster_op(ui: &mut egui::Ui, id_source: String, raster_op: &mut Option<RasterOp>) {
+
+    let name = |raster_op: &Option<RasterOp>| raster_op.map_or("Composite", |raster_op| raster_op.name());
+
+    egui::ComboBox::from_id_source(id_source)
+        .selected_text(name(raster_op))
+        .show_ui(ui, |ui| {
+            ui.selectable_value(raster_op, None, name(&None));
+            for option in RasterOp::ALL {
+                ui.selectable_value(raster_op, Some(option), option.name());
+            }
+        });
+}
@@ -4,14 +4,20 @@ use nalgebra_glm as glm;
 use crate::{
     shader_program::ShaderProgram,
     framebuffer::Framebuffer,
-    line::Line,
+    line::{Line, LineStyle, GradientParameter, ColorSpace, is_stipple_on, MAX_STIPPLE_RUNS},
     common::*,
     quad::Quad,
+    layers::Layers,
+    compositing::{composite, CompositeOp, BlendMode, RasterOp},
 };
 
-pub const CANVAS_WIDTH: u16 = WINDOW_WIDTH as u16;
-pub const CANVAS_HEIGHT: u16 = WINDOW_HEIGHT as u16;
+pub const DEFAULT_CANVAS_WIDTH: u16 = WINDOW_WIDTH as u16;
+pub const DEFAULT_CANVAS_HEIGHT: u16 = WINDOW_HEIGHT as u16;
 
+const PREVIEW_LINE_COLOR: ColorU8 = ColorU8 { r: 0, g: 200, b: 255, a: 255 };
+const TRANSPARENT: ColorU8 = ColorU8 { r: 0, g: 0, b: 0, a: 0 };
+
+#[derive(PartialEq, Clone, Copy)]
 pub enum LineAlgorithem {
     SlopeIntercept,
     SlopeInterceptFS,
@@ -19,16 +25,177 @@ pub enum LineAlgorithem {
     Bresenham,
 }
 
-enum LineKind {
+impl LineAlgorithem {
+
+    pub const ALL: [LineAlgorithem; 4] = [
+        LineAlgorithem::SlopeIntercept,
+        LineAlgorithem::SlopeInterceptFS,
+        LineAlgorithem::DDA,
+        LineAlgorithem::Bresenham,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            LineAlgorithem::SlopeIntercept => "Slope intercept",
+            LineAlgorithem::SlopeInterceptFS => "Slope intercept Fragment shader",
+            LineAlgorithem::DDA => "DDA",
+            LineAlgorithem::Bresenham => "Bresenham",
+        }
+    }
+
+    pub fn short_name(&self) -> &'static str {
+        match self {
+            LineAlgorithem::SlopeIntercept => "SI",
+            LineAlgorithem::SlopeInterceptFS => "SI FS",
+            LineAlgorithem::DDA => "DDA",
+            LineAlgorithem::Bresenham => "Bres.",
+        }
+    }
+}
+
+// Moderate lines are drawn along x with y = m * x + b, steep ones along y
+// with x = m * y + b.
+#[derive(PartialEq, Clone, Copy)]
+pub enum LineKind {
     Moderate,
     Steep,
 }
 
+impl LineKind {
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            LineKind::Moderate => "Moderate",
+            LineKind::Steep => "Steep",
+        }
+    }
+}
+
+// One pixel emitted by an algorithm. `pos` is the position it computed
+// before rounding to the pixel and `error` Bresenham's decision variable.
+pub struct TraceStep {
+    pub pixel: glm::U16Vec2,
+    pub pos: glm::Vec2,
+    pub error: Option<i32>,
+}
+
+// How a style spreads the pixels of a line's trace: across its width and
+// with gaps for its stipple. Stipples and gradient steps are counted along
+// the major axis and the width spans the minor one, so every algorithm agrees
+// with the GPU path. The pixel inspector goes through it too, so it reports
+// the pixels that were really painted.
+pub struct Stroke<'a> {
+    line: &'a Line,
+    stipple_runs: Vec<u32>,
+    stipple_offset: u32,
+    width_offsets: std::ops::RangeInclusive<i32>,
+    by_distance: bool,
+    tex_size: glm::I32Vec2,
+    start_pixel: glm::I32Vec2,
+    major: usize,
+    minor: usize,
+    major_length: u32,
+}
+
+impl<'a> Stroke<'a> {
+
+    pub fn new(
+        line: &'a Line,
+        style: &LineStyle,
+        stipple_offset: u32,
+        tex_width: u16,
+        tex_height: u16,
+    ) -> Self {
+
+        let (_, _, line_kind) = LinesRenderer::comput_m_b(line);
+        let half_size = glm::vec2(tex_width as i32 / 2, tex_height as i32 / 2);
+        let start_pixel = glm::vec2(line.start.x.round() as i32, line.start.y.round() as i32) + half_size;
+        let end_pixel = glm::vec2(line.end.x.round() as i32, line.end.y.round() as i32) + half_size;
+        let (major, minor) = match line_kind {
+            LineKind::Moderate => (0, 1),
+            LineKind::Steep => (1, 0),
+        };
+
+        Self {
+            line,
+            stipple_runs: style.stipple.runs(),
+            stipple_offset,
+            width_offsets: style.width_offsets(),
+            by_distance: matches!(
+                &style.gradient,
+                Some(gradient) if gradient.parameter == GradientParameter::Distance
+            ),
+            tex_size: glm::vec2(tex_width as i32, tex_height as i32),
+            start_pixel,
+            major,
+            minor,
+            major_length: (end_pixel[major] - start_pixel[major]).unsigned_abs(),
+        }
+    }
+
+    // Calls `paint_fn` with every pixel inside the canvas painted for the
+    // traced `pixel`, none in a stipple gap, and how far along the line it
+    // is for the gradient.
+    pub fn for_each_pixel(&self, pixel: &glm::U16Vec2, mut paint_fn: impl FnMut(glm::U16Vec2, f32)) {
+
+        let pixel = glm::vec2(pixel.x as i32, pixel.y as i32);
+        let step = (pixel[self.major] - self.start_pixel[self.major]).unsigned_abs();
+
+        if !is_stipple_on(&self.stipple_runs, step + self.stipple_offset) {
+            return;
+        }
+
+        for offset in self.width_offsets.clone() {
+
+            let mut pixel = pixel;
+            pixel[self.minor] += offset;
+
+            if !(0..self.tex_size.x).contains(&pixel.x) || !(0..self.tex_size.y).contains(&pixel.y) {
+                continue;
+            }
+
+            let t = if self.by_distance {
+                let center = pixel - self.tex_size / 2;
+                self.line.project(&glm::vec2(center.x as f32, center.y as f32))
+            } else if self.major_length == 0 {
+                0.0
+            } else {
+                (step as f32 / self.major_length as f32).min(1.0)
+            };
+
+            paint_fn(glm::U16Vec2::new(pixel.x as u16, pixel.y as u16), t);
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum LineRole {
+    Normal,
+    Preview,
+    // The preview on a persistent canvas, drawn in XOR so drawing it again
+    // the next frame erases it.
+    RubberBand,
+}
+
+// What a persistent canvas remembers between frames.
+struct PersistentCanvas {
+    texture: Vec<ColorU8>,
+    // The visible lines already on the canvas. Lines are inserted and
+    // reordered anywhere, so they are told apart by value.
+    drawn: Vec<Line>,
+    rubber_band: Option<(Line, LineAlgorithem)>,
+}
+
 pub struct LinesRenderer {
     line_shader: ShaderProgram,
     steap_line_shader: ShaderProgram,
-    canvas: Framebuffer,
+    canvases: Vec<Framebuffer>,
     back_color: Color,
+    transparent_background: bool,
+    // Overrides the raster operation of every line.
+    raster_op: Option<RasterOp>,
+    persistent: bool,
+    persistent_canvases: Vec<Option<PersistentCanvas>>,
     quad: Quad,
 }
 
@@ -49,19 +216,16 @@ impl Default for LinesRenderer {
                 )),
                 None,
             ),
-            canvas: Framebuffer::new(CANVAS_WIDTH, CANVAS_HEIGHT),
+            canvases: vec![Framebuffer::new(DEFAULT_CANVAS_WIDTH, DEFAULT_CANVAS_HEIGHT)],
             back_color: Color::default(),
+            transparent_background: false,
+            raster_op: None,
+            persistent: false,
+            persistent_canvases: vec![None],
             quad: Quad::default(),
         };
 
-        default_self.line_shader.set_uniform_vec2(
-            "canvas_size",
-            &glm::vec2(CANVAS_WIDTH as f32, CANVAS_HEIGHT as f32)
-        );
-        default_self.steap_line_shader.set_uniform_vec2(
-            "canvas_size",
-            &glm::vec2(CANVAS_WIDTH as f32, CANVAS_HEIGHT as f32)
-        );
+        default_self.set_canvas_size_uniforms();
 
         default_self
     }
@@ -69,57 +233,474 @@ impl Default for LinesRenderer {
 
 impl LinesRenderer {
 
-    pub fn render(&mut self, lines: &Vec<Line>, algorithem: &LineAlgorithem) {
+    // All the canvases have the same size.
+    pub fn get_canvas_size(&self) -> (u16, u16) {
+        self.canvases[0].get_size()
+    }
+
+    // Clears the canvases, the lines have to be rendered again.
+    pub fn set_canvas_size(&mut self, width: u16, height: u16) {
+        for canvas in &mut self.canvases {
+            canvas.resize(width, height);
+        }
+        self.set_canvas_size_uniforms();
+        self.clear_persistent_canvases();
+    }
 
-        match algorithem {
+    // Canvases are premultiplied, a transparent background lets the
+    // compositing operators that look at the destination alpha show.
+    pub fn is_background_transparent(&self) -> bool {
+        self.transparent_background
+    }
 
-            LineAlgorithem::SlopeInterceptFS => {
-                self.render_slope_intercept_gpu(lines);
-            }
+    pub fn set_background_transparent(&mut self, transparent: bool) {
+        self.transparent_background = transparent;
+        self.clear_persistent_canvases();
+    }
 
-            LineAlgorithem::SlopeIntercept => {
-                self.render_on_cpu(lines, Self::render_slope_intercept);
+    // None lets every line use its own.
+    pub fn get_raster_op(&self) -> Option<RasterOp> {
+        self.raster_op
+    }
+
+    pub fn set_raster_op(&mut self, raster_op: Option<RasterOp>) {
+        self.raster_op = raster_op;
+    }
+
+    // Persistent canvases are not rebuilt every frame, new lines are drawn
+    // over what is already there. Edited and deleted lines stay as they
+    // were drawn until the canvases are cleared.
+    pub fn is_persistent(&self) -> bool {
+        self.persistent
+    }
+
+    pub fn set_persistent(&mut self, persistent: bool) {
+        self.persistent = persistent;
+        self.clear_persistent_canvases();
+    }
+
+    // Back to the background, every line is drawn again the next frame.
+    pub fn clear_persistent_canvases(&mut self) {
+        for persistent_canvas in &mut self.persistent_canvases {
+            *persistent_canvas = None;
+        }
+    }
+
+    // One canvas per view, so each can show a different algorithm.
+    pub fn set_canvas_count(&mut self, count: usize) {
+        let (width, height) = self.get_canvas_size();
+        self.canvases.resize_with(count.max(1), || Framebuffer::new(width, height));
+        self.persistent_canvases.resize_with(count.max(1), || None);
+    }
+
+    fn set_canvas_size_uniforms(&self) {
+
+        let (width, height) = self.get_canvas_size();
+
+        self.line_shader.set_uniform_vec2(
+            "canvas_size",
+            &glm::vec2(width as f32, height as f32)
+        );
+        self.steap_line_shader.set_uniform_vec2(
+            "canvas_size",
+            &glm::vec2(width as f32, height as f32)
+        );
+    }
+
+    // Every visible layer is drawn with its algorithm or the view's one,
+    // and faded over the layers below with its opacity. The preview is
+    // drawn with the active layer.
+    pub fn render(
+        &mut self,
+        canvas: usize,
+        lines: &[Line],
+        layers: &Layers,
+        algorithem: &LineAlgorithem,
+        preview: Option<&Line>,
+    ) {
+
+        if self.persistent {
+            self.render_persistent(canvas, lines, layers, algorithem, preview);
+            return;
+        }
+
+        let (tex_width, tex_height) = self.get_canvas_size();
+        let mut texture = vec![self.get_back_color(); tex_width as usize * tex_height as usize];
+
+        for (index, layer) in layers.get_layers().iter().enumerate() {
+
+            if !layer.visible {
+                continue;
             }
 
-            LineAlgorithem::DDA => {
-                self.render_on_cpu(lines, Self::render_dda);
+            let layer_lines: Vec<(&Line, LineRole)> = lines
+                .iter()
+                .filter(|line| layers.get_index(line.layer) == Some(index))
+                .map(|line| (line, LineRole::Normal))
+                .chain(
+                    preview
+                        .filter(|_| layer.id == layers.get_active())
+                        .map(|line| (line, LineRole::Preview))
+                )
+                .collect();
+
+            if layer_lines.is_empty() {
+                continue;
             }
 
-            LineAlgorithem::Bresenham => {
-                self.render_on_cpu(lines, Self::render_bresenham);
+            match layer.algorithem.unwrap_or(*algorithem) {
+
+                LineAlgorithem::SlopeInterceptFS => {
+                    let layer_pixels = self.render_slope_intercept_gpu(canvas, &layer_lines);
+                    composite_layer(&mut texture, &layer_pixels, layer.opacity);
+                }
+
+                // Drawn straight over the layers below, so the compositing
+                // operators and the raster operations see them.
+                cpu_algorithem => {
+                    let mut layer_pixels = texture.clone();
+                    self.draw_on_cpu(
+                        &mut layer_pixels,
+                        &layer_lines,
+                        &Self::stipple_offsets(&layer_lines),
+                        Self::cpu_render_fn(&cpu_algorithem),
+                    );
+                    fade_layer(&mut texture, &layer_pixels, layer.opacity);
+                }
             }
         }
+
+        self.canvases[canvas].set_color_data(&texture);
     }
 
-    pub fn render_on_cpu(
+    // Only the lines added since the last frame are drawn, straight onto
+    // the canvas with their layer's algorithm. Layer opacity is ignored and
+    // the fragment shader is emulated, so raster operations work with every
+    // algorithm. Where lines overlap, or share an endpoint, pixels are drawn
+    // more than once: XOR erases them.
+    fn render_persistent(
         &mut self,
-        lines: &Vec<Line>,
+        canvas: usize,
+        lines: &[Line],
+        layers: &Layers,
+        algorithem: &LineAlgorithem,
+        preview: Option<&Line>,
+    ) {
+
+        let (tex_width, tex_height) = self.get_canvas_size();
+        let back_color = self.get_back_color();
+
+        let mut persistent_canvas = self.persistent_canvases[canvas].take().unwrap_or_else(|| PersistentCanvas {
+            texture: vec![back_color; tex_width as usize * tex_height as usize],
+            drawn: Vec::new(),
+            rubber_band: None,
+        });
+
+        // Drawing last frame's preview again erases it.
+        if let Some((line, line_algorithem)) = persistent_canvas.rubber_band.take() {
+            self.draw_on_cpu(
+                &mut persistent_canvas.texture,
+                &[(&line, LineRole::RubberBand)],
+                &Self::stipple_offsets(&[(&line, LineRole::RubberBand)]),
+                Self::cpu_render_fn(&line_algorithem),
+            );
+        }
+
+        let visible_lines: Vec<&Line> = lines.iter().filter(|line| layers.is_visible(line.layer)).collect();
+
+        // Deleting lines leaves them on the canvas.
+        let new_lines = Self::new_lines(&persistent_canvas.drawn, &visible_lines);
+
+        // Layer by layer like the other canvases. The stipple offsets count
+        // the lines already drawn, so a polyline continues its pattern.
+        for (index, layer) in layers.get_layers().iter().enumerate() {
+
+            let layer_lines: Vec<(&Line, LineRole)> = visible_lines
+                .iter()
+                .filter(|line| layers.get_index(line.layer) == Some(index))
+                .map(|&line| (line, LineRole::Normal))
+                .collect();
+
+            let (layer_new_lines, stipple_offsets): (Vec<_>, Vec<_>) = layer_lines
+                .iter()
+                .zip(Self::stipple_offsets(&layer_lines))
+                .filter(|((line, _), _)| new_lines.iter().any(|new_line| std::ptr::eq(*new_line, *line)))
+                .map(|(line, stipple_offset)| (*line, stipple_offset))
+                .unzip();
+
+            if !layer_new_lines.is_empty() {
+                self.draw_on_cpu(
+                    &mut persistent_canvas.texture,
+                    &layer_new_lines,
+                    &stipple_offsets,
+                    Self::cpu_render_fn(&layer.algorithem.unwrap_or(*algorithem)),
+                );
+            }
+        }
+        persistent_canvas.drawn = visible_lines.into_iter().cloned().collect();
+
+        if let Some(line) = preview {
+            let line_algorithem = layers.get_algorithem(layers.get_active(), *algorithem);
+            self.draw_on_cpu(
+                &mut persistent_canvas.texture,
+                &[(line, LineRole::RubberBand)],
+                &Self::stipple_offsets(&[(line, LineRole::RubberBand)]),
+                Self::cpu_render_fn(&line_algorithem),
+            );
+            persistent_canvas.rubber_band = Some((line.clone(), line_algorithem));
+        }
+
+        self.canvases[canvas].set_color_data(&persistent_canvas.texture);
+        self.persistent_canvases[canvas] = Some(persistent_canvas);
+    }
+
+    // The lines that are not among the drawn ones, each drawn line accounts
+    // for one equal line so duplicates are drawn again.
+    fn new_lines<'a>(drawn: &[Line], lines: &[&'a Line]) -> Vec<&'a Line> {
+
+        let mut unmatched: Vec<Option<&Line>> = drawn.iter().map(Some).collect();
+        let mut new_lines = Vec::new();
+
+        for (index, &line) in lines.iter().enumerate() {
+
+            // Mostly the lines are where they were.
+            let position = match unmatched.get(index) {
+                Some(&Some(drawn_line)) if drawn_line == line => Some(index),
+                _ => unmatched.iter().position(|&drawn_line| drawn_line == Some(line)),
+            };
+
+            match position {
+                Some(position) => unmatched[position] = None,
+                None => new_lines.push(line),
+            }
+        }
+
+        new_lines
+    }
+
+    // Premultiplied, like the canvases.
+    fn get_back_color(&self) -> ColorU8 {
+
+        if self.transparent_background {
+            return TRANSPARENT;
+        }
+
+        let to_u8 = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+        ColorU8 {
+            r: to_u8(self.back_color.r),
+            g: to_u8(self.back_color.g),
+            b: to_u8(self.back_color.b),
+            a: to_u8(self.back_color.a),
+        }
+    }
+
+    fn cpu_render_fn(algorithem: &LineAlgorithem) -> fn(&Line, u16, u16) -> Vec<glm::U16Vec2> {
+        match algorithem {
+            LineAlgorithem::SlopeIntercept => Self::render_slope_intercept,
+            LineAlgorithem::SlopeInterceptFS => |line, tex_width, tex_height| {
+                Self::rasterize(&LineAlgorithem::SlopeInterceptFS, line, tex_width, tex_height)
+            },
+            LineAlgorithem::DDA => Self::render_dda,
+            LineAlgorithem::Bresenham => Self::render_bresenham,
+        }
+    }
+
+    fn draw_on_cpu(
+        &self,
+        texture: &mut [ColorU8],
+        lines: &[(&Line, LineRole)],
+        stipple_offsets: &[u32],
         line_render_fn: fn(&Line, u16, u16) -> Vec<glm::U16Vec2>
     ) {
 
-        let (tex_width, tex_height) = self.canvas.get_size();
-        let size = (tex_width as usize * tex_height as usize) as usize;
-        let mut texture = vec![ColorU8::default(); size];
+        let (tex_width, tex_height) = self.get_canvas_size();
+
+        for ((line, role), &stipple_offset) in lines.iter().zip(stipple_offsets) {
 
-        for line in lines {
+            let line_pixels = line_render_fn(line, tex_width, tex_height);
 
-            let line_pixels = line_render_fn(&line, tex_width, tex_height);
+            let style = self.role_style(line, role);
+            let stroke = Stroke::new(line, &style, stipple_offset, tex_width, tex_height);
 
             for pixel_pos in &line_pixels {
+                stroke.for_each_pixel(pixel_pos, |pixel_pos, t| {
+                    let index = pixel_pos.y as usize * tex_width as usize + pixel_pos.x as usize;
+                    let color = style.color_at(t);
+                    texture[index] = match style.raster_op {
+                        Some(raster_op) => raster_op.apply(&color, &texture[index]),
+                        None => composite(&color, &texture[index], style.operator, style.blend_mode),
+                    };
+                });
+            }
+        }
+    }
 
-                let index: usize = (
-                      pixel_pos.y as usize
-                    * tex_width as usize
-                    + pixel_pos.x as usize
-                ) as usize;
+    // The preview is drawn in a single color, so it stands out from the
+    // lines it is previewing.
+    fn role_style(&self, line: &Line, role: &LineRole) -> LineStyle {
+        match role {
+            LineRole::Normal => LineStyle {
+                raster_op: self.raster_op.or(line.style.raster_op),
+                ..line.style.clone()
+            },
+            LineRole::Preview => LineStyle {
+                color: PREVIEW_LINE_COLOR,
+                gradient: None,
+                operator: CompositeOp::SrcOver,
+                blend_mode: BlendMode::Normal,
+                raster_op: None,
+                ..line.style.clone()
+            },
+            LineRole::RubberBand => LineStyle {
+                color: PREVIEW_LINE_COLOR,
+                gradient: None,
+                raster_op: Some(RasterOp::Xor),
+                ..line.style.clone()
+            },
+        }
+    }
 
-                if index < texture.len() {
-                    texture[index] = ColorU8{r:255, g: 255, b: 255, a:255};
-                }
+    // Where each line starts in its stipple pattern. A line starting where
+    // the previous one ends continues its pattern, so polylines do not
+    // restart it at every corner.
+    fn stipple_offsets(lines: &[(&Line, LineRole)]) -> Vec<u32> {
+
+        let round = |pos: &glm::Vec2| glm::vec2(pos.x.round() as i32, pos.y.round() as i32);
+
+        let mut offsets = Vec::with_capacity(lines.len());
+        let mut carried = 0;
+        let mut previous_end = None;
+
+        for (line, _) in lines {
+
+            if previous_end != Some(round(&line.start)) {
+                carried = 0;
+            }
+
+            offsets.push(line.style.stipple.get_phase() + carried);
+
+            let (_, _, line_kind) = Self::comput_m_b(line);
+            let major = match line_kind {
+                LineKind::Moderate => 0,
+                LineKind::Steep => 1,
+            };
+            carried += (round(&line.end)[major] - round(&line.start)[major]).unsigned_abs();
+            previous_end = Some(round(&line.end));
+        }
+
+        offsets
+    }
+
+    // The stipple offset of every line, as the lines are drawn layer by
+    // layer.
+    pub fn drawn_stipple_offsets(lines: &[Line], layers: &Layers) -> Vec<u32> {
+
+        let mut offsets = vec![0; lines.len()];
+
+        for layer in layers.get_layers() {
+
+            let (indices, layer_lines): (Vec<usize>, Vec<(&Line, LineRole)>) = lines
+                .iter()
+                .enumerate()
+                .filter(|(_, line)| line.layer == layer.id)
+                .map(|(i, line)| (i, (line, LineRole::Normal)))
+                .unzip();
+
+            for (i, offset) in indices.into_iter().zip(Self::stipple_offsets(&layer_lines)) {
+                offsets[i] = offset;
             }
+        }
+
+        offsets
+    }
+
+    // The pixels of a line inside the canvas. The fragment shader algorithm
+    // is emulated on the CPU.
+    pub fn rasterize(
+        algorithem: &LineAlgorithem,
+        line: &Line,
+        tex_width: u16,
+        tex_height: u16,
+    ) -> Vec<glm::U16Vec2> {
+
+        Self::trace(algorithem, line, tex_width, tex_height)
+            .into_iter()
+            .map(|step| step.pixel)
+            .filter(|pixel_pos| pixel_pos.x < tex_width && pixel_pos.y < tex_height)
+            .collect()
+    }
+
+    // Every pixel in the order the algorithm emits it, along with its
+    // internal state at that step. Pixels outside the canvas are kept.
+    pub fn trace(
+        algorithem: &LineAlgorithem,
+        line: &Line,
+        tex_width: u16,
+        tex_height: u16,
+    ) -> Vec<TraceStep> {
 
+        match algorithem {
+            LineAlgorithem::SlopeIntercept => Self::trace_slope_intercept(line, tex_width, tex_height),
+            LineAlgorithem::SlopeInterceptFS => Self::trace_slope_intercept_fs(line, tex_width, tex_height),
+            LineAlgorithem::DDA => Self::trace_dda(line, tex_width, tex_height),
+            LineAlgorithem::Bresenham => Self::trace_bresenham(line, tex_width, tex_height),
+        }
+    }
+
+    // Like the shader, lights every fragment of the canvas on the infinite
+    // line, not only the segment.
+    fn trace_slope_intercept_fs(
+        line: &Line,
+        tex_width: u16,
+        tex_height: u16,
+    ) -> Vec<TraceStep> {
+
+        let (m, b, line_kind) = LinesRenderer::comput_m_b(line);
+
+        if m.is_nan() {
+            return Vec::new();
         }
-        self.canvas.set_color_data(&texture);
+
+        let (columns, rows) = match line_kind {
+            LineKind::Moderate => (tex_width as i32, tex_height as i32),
+            LineKind::Steep => (tex_height as i32, tex_width as i32),
+        };
+
+        (0..columns)
+            .filter_map(|column| {
+                let x = (column - columns / 2) as f32;
+                let y = m * x + b;
+                let row = y.round() as i32 + rows / 2;
+                if !(0..rows).contains(&row) {
+                    return None;
+                }
+                Some(match line_kind {
+                    LineKind::Moderate => TraceStep {
+                        pixel: glm::U16Vec2::new(column as u16, row as u16),
+                        pos: glm::vec2(x, y),
+                        error: None,
+                    },
+                    LineKind::Steep => TraceStep {
+                        pixel: glm::U16Vec2::new(row as u16, column as u16),
+                        pos: glm::vec2(y, x),
+                        error: None,
+                    },
+                })
+            })
+            .collect()
+    }
+
+    // The canvas shows the given pixels instead of rendered lines.
+    pub fn set_canvas_pixels(&mut self, canvas: usize, pixels: &[ColorU8]) {
+        self.canvases[canvas].set_color_data(pixels);
+    }
+
+    pub fn read_canvas_pixels(&self, canvas: usize) -> Vec<ColorU8> {
+        self.canvases[canvas].read_color_data()
+    }
+
+    pub fn read_canvas_pixel(&self, canvas: usize, pixel: &glm::U16Vec2) -> ColorU8 {
+        self.canvases[canvas].read_pixel(pixel.x, pixel.y)
     }
 
     pub fn render_bresenham(
@@ -127,6 +708,17 @@ impl LinesRenderer {
         tex_width: u16,
         tex_height: u16,
     ) -> Vec<glm::U16Vec2> {
+        Self::trace_bresenham(line, tex_width, tex_height)
+            .into_iter()
+            .map(|step| step.pixel)
+            .collect()
+    }
+
+    pub fn trace_bresenham(
+        line: &Line,
+        tex_width: u16,
+        tex_height: u16,
+    ) -> Vec<TraceStep> {
 
         let xi = line.start.x.round() as i32;
         let yi = line.start.y.round() as i32;
@@ -154,12 +746,10 @@ impl LinesRenderer {
         xf: i32, yf: i32,
         tex_width: u16,
         tex_height: u16,
-    ) -> Vec<glm::U16Vec2> {
+    ) -> Vec<TraceStep> {
 
-        let mut line_pixels = vec![
-            glm::U16Vec2::from_element(0);
-            (xf - xi + 1) as usize
-        ];
+        let pixels_count = (xf - xi + 1) as usize;
+        let mut line_pixels = Vec::with_capacity(pixels_count);
 
         let dx = xf - xi;
         let dy = yf - yi;
@@ -168,13 +758,16 @@ impl LinesRenderer {
         let mut e = (2 * dy) - dx;
 
         let mut y = yi;
-        let mut x = xi;
 
-        for i in 0..line_pixels.len() {
+        for x in xi..=xf {
 
             let tex_x = (x + tex_width as i32 / 2) as u16;
             let tex_y = (y + tex_height as i32 / 2) as u16;
-            line_pixels[i] = glm::U16Vec2::new(tex_x, tex_y);
+            line_pixels.push(TraceStep {
+                pixel: glm::U16Vec2::new(tex_x, tex_y),
+                pos: glm::vec2(x as f32, y as f32),
+                error: Some(e),
+            });
 
             if e > 0 {
                 y = y + ys;
@@ -182,7 +775,6 @@ impl LinesRenderer {
             } else {
                 e = e + 2 * dy;
             }
-            x += 1;
         }
 
         line_pixels
@@ -193,12 +785,10 @@ impl LinesRenderer {
         xf: i32, yf: i32,
         tex_width: u16,
         tex_height: u16,
-    ) -> Vec<glm::U16Vec2> {
+    ) -> Vec<TraceStep> {
 
-        let mut line_pixels = vec![
-            glm::U16Vec2::from_element(0);
-            (yf - yi + 1) as usize
-        ];
+        let pixels_count = (yf - yi + 1) as usize;
+        let mut line_pixels = Vec::with_capacity(pixels_count);
 
         let dx = xf - xi;
         let dy = yf - yi;
@@ -206,14 +796,17 @@ impl LinesRenderer {
 
         let mut e = (2 * dx) - dy;
 
-        let mut y = yi;
         let mut x = xi;
 
-        for i in 0..line_pixels.len() {
+        for y in yi..=yf {
 
             let tex_x = (x + tex_width as i32 / 2) as u16;
             let tex_y = (y + tex_height as i32 / 2) as u16;
-            line_pixels[i] = glm::U16Vec2::new(tex_x, tex_y);
+            line_pixels.push(TraceStep {
+                pixel: glm::U16Vec2::new(tex_x, tex_y),
+                pos: glm::vec2(x as f32, y as f32),
+                error: Some(e),
+            });
 
             if e > 0 {
                 x = x + xs;
@@ -221,7 +814,6 @@ impl LinesRenderer {
             } else {
                 e = e + 2 * dx;
             }
-            y += 1;
         }
 
         line_pixels
@@ -232,6 +824,17 @@ impl LinesRenderer {
         tex_width: u16,
         tex_height: u16,
     ) -> Vec<glm::U16Vec2> {
+        Self::trace_dda(line, tex_width, tex_height)
+            .into_iter()
+            .map(|step| step.pixel)
+            .collect()
+    }
+
+    pub fn trace_dda(
+        line: &Line,
+        tex_width: u16,
+        tex_height: u16,
+    ) -> Vec<TraceStep> {
 
         let d_pos = line.end - line.start;
         let dx = d_pos.x;
@@ -247,17 +850,22 @@ impl LinesRenderer {
         let dx = dx / m;
         let dy = dy / m;
 
-        let mut line_pixels = vec![glm::U16Vec2::from_element(0); m.round() as usize];
+        let pixels_count = m.round() as usize;
+        let mut line_pixels = Vec::with_capacity(pixels_count);
 
         let mut x = line.start.x;
         let mut y = line.start.y;
 
-        for i in 0..line_pixels.len() {
+        for _ in 0..pixels_count {
 
             let tex_x = (x + tex_width as f32 / 2.0).round() as u16;
             let tex_y = (y + tex_height as f32 / 2.0).round() as u16;
 
-            line_pixels[i] = glm::U16Vec2::new(tex_x, tex_y);
+            line_pixels.push(TraceStep {
+                pixel: glm::U16Vec2::new(tex_x, tex_y),
+                pos: glm::vec2(x, y),
+                error: None,
+            });
 
             x += dx;
             y += dy;
@@ -271,6 +879,17 @@ impl LinesRenderer {
         tex_width: u16,
         tex_height: u16,
     ) -> Vec<glm::U16Vec2> {
+        Self::trace_slope_intercept(line, tex_width, tex_height)
+            .into_iter()
+            .map(|step| step.pixel)
+            .collect()
+    }
+
+    pub fn trace_slope_intercept(
+        line: &Line,
+        tex_width: u16,
+        tex_height: u16,
+    ) -> Vec<TraceStep> {
 
         let (m, b, line_kind) = LinesRenderer::comput_m_b(line);
         let mut line_pixels;
@@ -282,15 +901,20 @@ impl LinesRenderer {
                 let (start, end) = LinesRenderer::x_order_line_ends(line);
                 let pixels_count = (end.x - start.x + 1.0) as usize;
 
-                line_pixels = vec![glm::U16Vec2::from_element(0); pixels_count];
+                line_pixels = Vec::with_capacity(pixels_count);
 
-                for i in 0..line_pixels.len() {
+                for i in 0..pixels_count {
 
                     let x = i as f32 + start.x;
-                    let y = ((m * x + b) + tex_height as f32 / 2.0).round() as u16;
-                    let x = (x + tex_width as f32 / 2.0) as u16;
-
-                    line_pixels[i] = glm::U16Vec2::new(x,y);
+                    let y = m * x + b;
+                    let tex_y = (y + tex_height as f32 / 2.0).round() as u16;
+                    let tex_x = (x + tex_width as f32 / 2.0) as u16;
+
+                    line_pixels.push(TraceStep {
+                        pixel: glm::U16Vec2::new(tex_x, tex_y),
+                        pos: glm::vec2(x, y),
+                        error: None,
+                    });
                 }
             }
 
@@ -299,15 +923,20 @@ impl LinesRenderer {
                 let (start, end) = LinesRenderer::y_order_line_ends(line);
                 let pixels_count = (end.y - start.y + 1.0).abs() as usize;
 
-                line_pixels = vec![glm::U16Vec2::from_element(0); pixels_count];
+                line_pixels = Vec::with_capacity(pixels_count);
 
-                for i in 0..line_pixels.len() {
+                for i in 0..pixels_count {
 
                     let y = i as f32 + start.y;
-                    let x = ((m * y + b) + tex_width as f32 / 2.0).round() as u16;
-                    let y = (y + tex_height as f32 / 2.0) as u16;
-
-                    line_pixels[i] = glm::U16Vec2::new(x, y);
+                    let x = m * y + b;
+                    let tex_x = (x + tex_width as f32 / 2.0).round() as u16;
+                    let tex_y = (y + tex_height as f32 / 2.0) as u16;
+
+                    line_pixels.push(TraceStep {
+                        pixel: glm::U16Vec2::new(tex_x, tex_y),
+                        pos: glm::vec2(x, y),
+                        error: None,
+                    });
                 }
             }
         }
@@ -315,40 +944,82 @@ impl LinesRenderer {
         line_pixels
     }
 
-    pub fn render_slope_intercept_gpu(&self, lines: &Vec<Line>) {
+    // Uses the canvas to draw over transparent black and reads it back, like
+    // the CPU algorithms.
+    fn render_slope_intercept_gpu(&self, canvas: usize, lines: &[(&Line, LineRole)]) -> Vec<ColorU8> {
 
-        self.canvas.clear(&self.back_color);
-        self.canvas.bind();
+        let canvas = &self.canvases[canvas];
 
-        for line in lines {
+        canvas.clear(&Color { r: 0.0, g: 0.0, b: 0.0, a: 0.0 });
+        canvas.bind();
 
-            let (m, b, line_kind) = LinesRenderer::comput_m_b(line);
-            let shader;
+        unsafe {
+            gl::Enable(gl::BLEND);
+            gl::BlendFuncSeparate(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA, gl::ONE, gl::ONE_MINUS_SRC_ALPHA);
+        }
 
-            match line_kind {
+        for ((line, role), stipple_offset) in lines.iter().zip(Self::stipple_offsets(lines)) {
 
-                LineKind::Moderate => {
-                    shader = &self.line_shader;
-                }
+            let (m, b, line_kind) = LinesRenderer::comput_m_b(line);
 
-                LineKind::Steep => {
-                    shader = &self.steap_line_shader;
-                }
-            }
+            let (shader, major_start, major_end) = match line_kind {
+                LineKind::Moderate => (&self.line_shader, line.start.x.round(), line.end.x.round()),
+                LineKind::Steep => (&self.steap_line_shader, line.start.y.round(), line.end.y.round()),
+            };
+
+            let style = self.role_style(line, role);
+            let to_vec4 = |color: &ColorU8| {
+                glm::vec4(color.r as f32, color.g as f32, color.b as f32, color.a as f32) / 255.0
+            };
 
+            shader.set_uniform_vec4("line_color", &to_vec4(&style.color));
             shader.set_uniform_f32("m", m);
             shader.set_uniform_f32("b", b);
+            shader.set_uniform_f32("line_width", style.width.max(1) as f32);
+            shader.set_uniform_f32("major_start", major_start);
+            shader.set_uniform_f32("major_length", (major_end - major_start).abs());
+
+            // Without a gradient it goes to the same color.
+            let (end_color, linear, by_distance) = match &style.gradient {
+                Some(gradient) => (
+                    &gradient.end_color,
+                    gradient.space == ColorSpace::Linear,
+                    gradient.parameter == GradientParameter::Distance,
+                ),
+                None => (&style.color, false, false),
+            };
+            shader.set_uniform_vec4("line_end_color", &to_vec4(end_color));
+            shader.set_uniform_bool("gradient_linear", linear);
+            shader.set_uniform_bool("gradient_by_distance", by_distance);
+            shader.set_uniform_vec2("line_start", &line.start);
+            shader.set_uniform_vec2("line_end", &line.end);
+
+            let runs = style.stipple.runs();
+            let mut stipple_runs = [0.0; MAX_STIPPLE_RUNS];
+            for (run, length) in stipple_runs.iter_mut().zip(&runs) {
+                *run = *length as f32;
+            }
+            shader.set_uniform_f32_array("stipple_runs", &stipple_runs);
+            shader.set_uniform_i32("stipple_run_count", runs.len() as i32);
+            shader.set_uniform_f32("stipple_period", runs.iter().sum::<u32>() as f32);
+            shader.set_uniform_f32("stipple_offset", stipple_offset as f32);
             self.quad.render(shader);
         }
 
-        self.canvas.unbind();
+        unsafe {
+            gl::Disable(gl::BLEND);
+        }
+
+        canvas.unbind();
+
+        canvas.read_color_data()
     }
 
-    pub fn use_canvas_color_attachment(&self) {
-        self.canvas.use_color_attachment()
+    pub fn use_canvas_color_attachment(&self, canvas: usize) {
+        self.canvases[canvas].use_color_attachment()
     }
 
-    fn comput_m_b(line: &Line) -> (f32, f32, LineKind) {
+    pub fn comput_m_b(line: &Line) -> (f32, f32, LineKind) {
 
         let dx = line.end.x - line.start.x;
         let dy = line.end.y - line.start.y;
@@ -388,3 +1059,49 @@ impl LinesRenderer {
         }
     }
 }
+
+// Premultiplied layer pixels over the canvas, faded by the layer opacity.
+fn composite_layer(dst: &mut [ColorU8], layer: &[ColorU8], opacity: f32) {
+
+    let opacity = (opacity.clamp(0.0, 1.0) * 255.0).round() as u32;
+    let fade = |value: u8| (value as u32 * opacity + 127) / 255;
+
+    for (dst, src) in dst.iter_mut().zip(layer) {
+
+        if src.a == 0 {
+            continue;
+        }
+
+        let src_alpha = fade(src.a);
+        let mix = |src: u8, dst: u8| (fade(src) + (dst as u32 * (255 - src_alpha) + 127) / 255).min(255) as u8;
+
+        *dst = ColorU8 {
+            r: mix(src.r, dst.r),
+            g: mix(src.g, dst.g),
+            b: mix(src.b, dst.b),
+            a: mix(src.a, dst.a),
+        };
+    }
+}
+
+// The layer drawn over `dst` replaces it by `opacity`. For source over this
+// is the same as compositing the layer on its own.
+fn fade_layer(dst: &mut [ColorU8], layer: &[ColorU8], opacity: f32) {
+
+    if opacity >= 1.0 {
+        dst.clone_from_slice(layer);
+        return;
+    }
+
+    let opacity = opacity.max(0.0);
+    let mix = |src: u8, dst: u8| (dst as f32 + (src as f32 - dst as f32) * opacity).round() as u8;
+
+    for (dst, src) in dst.iter_mut().zip(layer) {
+        *dst = ColorU8 {
+            r: mix(src.r, dst.r),
+            g: mix(src.g, dst.g),
+            b: mix(src.b, dst.b),
+            a: mix(src.a, dst.a),
+        };
+    }
+}
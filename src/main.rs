@@ -9,20 +9,55 @@ mod shader_program;
 mod framebuffer;
 mod texture;
 mod screen;
+mod camera;
 mod line;
 mod lines_renderer;
+mod curves;
+mod svg;
+mod dxf;
+mod history;
+mod selection;
+mod scene;
+mod snapping;
+mod line_table;
+mod rulers;
+mod split_view;
+mod difference;
+mod visualizer;
+mod gif;
+mod png;
+mod recording;
+mod inspector;
+mod measure;
+mod layers;
+mod scene_graph;
+mod gizmo;
+mod compositing;
 
 use nalgebra_glm as glm;
 use egui_glfw_gl as egui_backend;
 use egui_backend::glfw;
+use egui_backend::egui;
 
-use line::Line;
-use screen::Screen;
-use lines_renderer::{LinesRenderer, LineAlgorithem};
+use line::{Line, LineStyle};
+use screen::{Screen, ScreenOverlay};
+use camera::Camera2D;
+use lines_renderer::{LinesRenderer, LineAlgorithem, DEFAULT_CANVAS_WIDTH, DEFAULT_CANVAS_HEIGHT};
+use selection::{Tool, HIT_DISTANCE};
+use scene::Scene;
+use line_table::{LineTable, edit_raster_op};
+use split_view::{SplitView, SplitMode};
+use difference::DifferenceView;
+use visualizer::Visualizer;
+use recording::{Recorder, RecordingFormat};
+use inspector::{PixelInspector, PixelInspection};
+use measure::Measurement;
+use gizmo::{Gizmo, GizmoMode, GIZMO_SIZE};
+use snapping::{Snap, SnapSettings, SnapKind, AngleStep, SNAP_DISTANCE};
+use dxf::DxfUnits;
 
 use gl::{self, types::{GLenum, GLuint, GLsizei, GLchar}};
 use common::{WINDOW_WIDTH, WINDOW_HEIGHT};
-use lines_renderer::{CANVAS_HEIGHT, CANVAS_WIDTH};
 use ui::Gui;
 
 extern "system" fn gl_debug_proc(
@@ -52,17 +87,21 @@ fn main() {
         //gl::Enable(gl::DEBUG_OUTPUT);
     }
 
+    // In screen pixels per second.
     let move_speed = glm::Vec2::new(
-        CANVAS_WIDTH as f32 / 500.0, CANVAS_HEIGHT as f32 / 500.0
+        WINDOW_WIDTH as f32 * 0.8, WINDOW_HEIGHT as f32 * 0.8
     );
 
     let mut screen = Screen::default();
+    let mut view = View::default();
 
     let mut lines_renderer = LinesRenderer::default();
     let mut line_algorithem = LineAlgorithem::SlopeIntercept;
 
-    let mut lines: Vec<Line> = Vec::new();
-    let mut line_start: Option<glm::Vec2> = None;
+    let mut scene = Scene::default();
+    let mut editor = Editor::default();
+
+    let mut file_settings = FileSettings::default();
 
     let mut start = Instant::now();
     let mut dt = Duration::from_secs_f32(1.0 / 60.0);
@@ -71,36 +110,213 @@ fn main() {
 
         gui.start_frame();
 
-        screen.update_zoom(gui.consume_scroll_amount());
+        if let Some((width, height)) = gui.consume_resize() {
+            resize_window(&gui, &mut screen, &mut view, width, height);
+        }
+
+        let (canvas_width, canvas_height) = lines_renderer.get_canvas_size();
+        let canvas_size = glm::vec2(canvas_width as f32, canvas_height as f32);
+
+        // Keeps the pane while dragging into another one.
+        if !gui.is_left_button_down() {
+            view.split.update_active_pane(&view.camera, &gui.get_cursor_pos());
+        }
+
+        let mut camera = view.get_active_camera();
+
+        camera.zoom_at(gui.consume_scroll_amount(), &gui.get_cursor_pos());
+
+        camera.pan(&gui.consume_pan_delta());
 
-        screen.move_canvas(&get_move_deltas(&gui, &dt, &move_speed));
+        camera.pan(&get_move_deltas(&gui, &dt, &move_speed));
+
+        view.set_camera(&camera);
+
+        // Undoing in the middle of a drag would fight with it.
+        if !scene.selection.is_dragging() && !editor.gizmo.is_dragging() {
+            if gui.consume_key_press(
+                glfw::Key::Z, glfw::Modifiers::Control | glfw::Modifiers::Shift
+            ) {
+                editor.commit_edits(&mut scene);
+                scene.redo();
+            }
+            else if gui.consume_key_press(glfw::Key::Z, glfw::Modifiers::Control) {
+                editor.commit_edits(&mut scene);
+                scene.undo();
+            }
+        }
+
+        if view.visualizer.is_active() {
+            if gui.consume_key_press(glfw::Key::Space, glfw::Modifiers::empty()) {
+                view.visualizer.toggle_playing();
+            }
+            if gui.consume_key_press(glfw::Key::Right, glfw::Modifiers::empty()) {
+                view.visualizer.step_forward();
+            }
+            if gui.consume_key_press(glfw::Key::Left, glfw::Modifiers::empty()) {
+                view.visualizer.step_back();
+            }
+        }
 
-        match (gui.consume_cursor_left_press_pos(), line_start) {
+        match editor.tool {
 
-            (Some(mut start_pos), None) => {
-                transform_pos(&mut start_pos, &screen);
-                line_start = Some(start_pos);
+            Tool::Draw => {
+                handle_draw_input(&mut gui, &camera, &mut scene, &mut editor);
             }
 
-            (Some(mut end_pos), Some(start_pos)) => {
-                transform_pos(&mut end_pos, &screen);
+            Tool::Select => {
+                handle_select_input(&mut gui, &camera, &mut scene);
+            }
 
-                lines.push(Line::new(start_pos, end_pos));
-                line_start = None;
+            Tool::Measure => {
+                handle_measure_input(&mut gui, &camera, &scene, &mut editor);
             }
 
-            (None, _) => {}
+            Tool::Transform => {
+                handle_transform_input(&mut gui, &camera, &mut scene, &mut editor.gizmo);
+            }
         }
 
-        lines_renderer.render(&lines, &line_algorithem);
+        let cursor_snap = match editor.tool {
+            Tool::Draw | Tool::Measure => Some(snap_cursor_pos(&gui, &camera, &scene, &editor)),
+            Tool::Select | Tool::Transform => None,
+        };
+
+        let preview = match (editor.line_start, &cursor_snap) {
+            (Some(start_pos), Some(snap)) => {
+                Some(Line::new(start_pos, snap.pos).with_style(editor.line_style.clone()))
+            }
+            _ => None,
+        };
+
+        let panes = view.split.pane_cameras(&view.camera);
+        let pixels_per_point = gui.get_pixels_per_point();
+
+        lines_renderer.set_canvas_count(panes.len());
+
+        let world_lines = scene.world_lines();
+        let visible_lines = scene.visible_lines();
+
+        view.difference.update(&visible_lines, canvas_width, canvas_height);
+
+        view.visualizer.update(
+            &world_lines,
+            scene.selection.get_selected(),
+            &line_algorithem,
+            canvas_width,
+            canvas_height,
+            &dt,
+        );
 
         screen.clear();
 
-        lines_renderer.use_canvas_color_attachment();
+        for (pane, pane_camera) in panes.iter().enumerate() {
 
-        screen.render_used_texture();
+            let algorithem = view.split.get_pane_algorithem(pane, line_algorithem);
+            let mut title = algorithem.name().to_string();
 
-        render_gui(&gui, &screen, &mut line_algorithem, &mut lines, &mut line_start);
+            // The visualizer or the difference map take the place of the
+            // first view.
+            match view.difference.get_map() {
+                _ if pane == 0 && view.visualizer.is_active() => {
+                    lines_renderer.set_canvas_pixels(
+                        pane, &view.visualizer.render_pixels(canvas_width, canvas_height)
+                    );
+                    title = format!("{} step by step", algorithem.name());
+                }
+                Some(map) if pane == 0 => {
+                    lines_renderer.set_canvas_pixels(pane, &map.pixels);
+                    title = format!(
+                        "{} vs {}",
+                        view.difference.algorithem_a.name(),
+                        view.difference.algorithem_b.name()
+                    );
+                }
+                _ => {
+                    lines_renderer.render(
+                        pane,
+                        &world_lines,
+                        &scene.layers,
+                        &algorithem,
+                        preview.as_ref(),
+                    );
+                }
+            }
+
+            if pane == view.split.get_active_pane() {
+                if gui.is_cursor_over_gui() {
+                    view.inspector.clear();
+                }
+                else {
+                    view.inspector.update(
+                        &lines_renderer,
+                        pane,
+                        algorithem,
+                        &world_lines,
+                        &scene.layers,
+                        &pane_camera.screen_to_canvas(&gui.get_cursor_pos()),
+                    );
+                }
+            }
+
+            if pane == 0 && view.recorder.is_recording() {
+                view.recorder.capture(
+                    lines_renderer.read_canvas_pixels(pane),
+                    (canvas_width, canvas_height),
+                    dt.as_secs_f32(),
+                );
+            }
+
+            lines_renderer.use_canvas_color_attachment(pane);
+
+            screen.render_used_texture(
+                pane_camera, &canvas_size, &view.overlay, gui.get_framebuffer_scale()
+            );
+
+            let pane_min = pane_camera.get_viewport_pos() / pixels_per_point;
+            let pane_max = pane_min + pane_camera.get_viewport_size() / pixels_per_point;
+            let painter = gui.get_overlay_painter().sub_region(egui::Rect::from_min_max(
+                egui::pos2(pane_min.x, pane_min.y), egui::pos2(pane_max.x, pane_max.y)
+            ));
+
+            paint_overlay(&painter, pixels_per_point, pane_camera, &view.overlay, &visible_lines);
+
+            if let Some(index) = scene.selection.get_selected() {
+                paint_selection_handles(&painter, pixels_per_point, pane_camera, &world_lines[index]);
+            }
+
+            if let (Tool::Transform, Some(group)) = (editor.tool, scene.graph.get_selected()) {
+                paint_gizmo(&painter, pixels_per_point, pane_camera, &scene.graph, group, editor.gizmo.mode);
+            }
+
+            if panes.len() > 1 {
+                paint_pane_frame(&painter, &title);
+            }
+
+            if let (Tool::Measure, Some(snap)) = (editor.tool, &cursor_snap) {
+                if let Some((start, end)) = editor.measurement.get_segment(&snap.pos) {
+                    paint_measurement(&painter, pixels_per_point, pane_camera, &start, &end);
+                }
+            }
+
+            if let (Some(snap), true) = (&cursor_snap, pane == view.split.get_active_pane()) {
+                paint_snap_indicator(&painter, pixels_per_point, pane_camera, snap.kind, &snap.pos);
+            }
+        }
+
+        if let (true, Some(inspection)) = (view.inspector.show_tooltip, view.inspector.get_inspection()) {
+            gui.show_tooltip(&gui.get_cursor_pos(), |ui| render_pixel_inspection(ui, inspection));
+        }
+
+        render_gui(
+            &gui,
+            &mut view,
+            &mut lines_renderer,
+            &mut line_algorithem,
+            &mut scene,
+            &mut editor,
+            &mut file_settings,
+        );
 
         gui.end_frame();
 
@@ -111,16 +327,70 @@ fn main() {
 
 fn render_gui(
     gui: &Gui,
-    screen: &Screen,
+    view: &mut View,
+    lines_renderer: &mut LinesRenderer,
     line_algorithem: &mut LineAlgorithem,
-    lines: &mut Vec<Line>,
-    line_start: &mut Option<glm::Vec2>
+    scene: &mut Scene,
+    editor: &mut Editor,
+    file_settings: &mut FileSettings,
 ) {
 
+        let (canvas_width, canvas_height) = lines_renderer.get_canvas_size();
+        let canvas_size = glm::vec2(canvas_width as f32, canvas_height as f32);
+
         gui.show(|ui| {
 
             ui.separator();
 
+            ui.horizontal(|ui| {
+                ui.label("Tool:");
+                if ui.radio(editor.tool == Tool::Draw, "Draw").clicked() {
+                    editor.tool = Tool::Draw;
+                    scene.selection.select(None);
+                }
+                if ui.radio(editor.tool == Tool::Select, "Select").clicked() {
+                    editor.tool = Tool::Select;
+                    editor.line_start = None;
+                }
+                if ui.radio(editor.tool == Tool::Measure, "Measure").clicked() {
+                    editor.tool = Tool::Measure;
+                    editor.line_start = None;
+                    scene.selection.select(None);
+                }
+                if ui.radio(editor.tool == Tool::Transform, "Transform").clicked() {
+                    editor.tool = Tool::Transform;
+                    editor.line_start = None;
+                    scene.selection.select(None);
+                }
+            });
+
+            if editor.tool == Tool::Transform {
+                ui.horizontal(|ui| {
+                    for mode in GizmoMode::ALL {
+                        ui.selectable_value(&mut editor.gizmo.mode, mode, mode.name());
+                    }
+                });
+                if scene.graph.get_selected().is_none() {
+                    ui.label("Select a group to transform it.");
+                }
+            }
+
+            if editor.tool == Tool::Measure {
+                let cursor_pos = snap_cursor_pos(gui, &view.get_active_camera(), scene, editor).pos;
+                match editor.measurement.get_segment(&cursor_pos) {
+                    Some((start, end)) => {
+                        ui.label(measurement_text(&start, &end));
+                    }
+                    None => {
+                        ui.label("Click two points to measure them.");
+                    }
+                }
+            }
+
+            render_snap_gui(ui, &mut editor.snap_settings);
+
+            ui.separator();
+
             if ui.radio(
                 matches!(line_algorithem, LineAlgorithem::SlopeIntercept),
                 "Slope intercept"
@@ -151,33 +421,93 @@ fn render_gui(
 
             ui.separator();
 
-            let mut cursor_pos = gui.get_cursor_pos();
-            transform_pos(&mut cursor_pos, screen);
+            let cursor_pos = view.get_active_camera().screen_to_canvas(&gui.get_cursor_pos());
 
             ui.label(format!(
                 "Cursor position: ({}, {})",
                 cursor_pos.x as i32, cursor_pos.y as i32
             ));
 
-            ui.separator();
+            if let Some(inspection) = view.inspector.get_inspection() {
+                render_pixel_inspection(ui, inspection);
+            }
+
+            ui.horizontal(|ui| {
+                ui.label(format!("Zoom: {:.0}%", view.camera.get_zoom() * 100.0));
+                let mut camera = view.get_active_camera();
+                if ui.button("Zoom to fit").clicked() {
+                    zoom_to_fit(&mut camera, &scene.world_lines(), &canvas_size);
+                    view.set_camera(&camera);
+                }
+                if ui.button("Reset view").clicked() {
+                    camera.reset(&canvas_size);
+                    view.set_camera(&camera);
+                }
+            });
 
-            ui.label("Lines (start -> end):");
-            if ui.button("clear").clicked() {
-                *line_start = None;
-                lines.clear();
+            let mut rotation = view.camera.get_rotation().to_degrees();
+            ui.horizontal(|ui| {
+                ui.label("Rotation:");
+                ui.add(egui::DragValue::new(&mut rotation).speed(0.5).suffix("°"));
+                if ui.button("0°").clicked() {
+                    rotation = 0.0;
+                }
+            });
+            if rotation != view.camera.get_rotation().to_degrees() {
+                view.camera.set_rotation(rotation.to_radians());
             }
 
-            for line in lines {
-                ui.label(format!(
-                    "({}, {}) -> ({}, {})",
-                    line.start.x as i32,
-                    line.start.y as i32,
-                    line.end.x as i32,
-                    line.end.y as i32,
-                ));
+            render_canvas_size_gui(ui, view, lines_renderer);
+
+            render_split_view_gui(ui, &mut view.split);
+
+            render_difference_gui(ui, &mut view.difference);
+
+            render_visualizer_gui(ui, &mut view.visualizer, (canvas_width, canvas_height));
+
+            render_recording_gui(ui, view, (canvas_width, canvas_height));
+
+            ui.collapsing("Overlay", |ui| {
+                ui.checkbox(&mut view.overlay.pixel_grid, "Pixel grid");
+                ui.checkbox(&mut view.overlay.pixel_centers, "Pixel centers");
+                ui.checkbox(&mut view.overlay.ideal_lines, "Ideal lines");
+                ui.checkbox(&mut view.overlay.rulers, "Rulers");
+                ui.checkbox(&mut view.overlay.endpoint_labels, "Endpoint labels");
+                ui.checkbox(&mut view.inspector.show_tooltip, "Pixel inspector tooltip");
+            });
+
+            ui.separator();
+
+            render_file_gui(ui, scene, file_settings, (canvas_width, canvas_height));
+
+            ui.separator();
+
+            render_history_gui(ui, scene, editor);
+
+            ui.separator();
+
+            render_layers_gui(ui, scene);
+
+            render_groups_gui(ui, scene, &mut editor.group_edit);
+
+            ui.separator();
+
+            ui.label("Lines:");
+            if ui.button("clear").on_hover_text("Lines on hidden and locked layers are kept").clicked() {
+                editor.line_start = None;
+                editor.commit_edits(scene);
+                scene.clear();
+            }
 
+            if let Some(index) = editor.line_table.show(
+                ui, scene, (canvas_width, canvas_height), &mut editor.line_style
+            ) {
+                editor.tool = Tool::Select;
+                editor.line_start = None;
+                scene.selection.select(Some(index));
             }
-            match line_start {
+
+            match editor.line_start {
                 Some(pos) => {
                     ui.label(format!("({}, {}) -> ", pos.x as i32, pos.y as i32));
                 }
@@ -187,26 +517,1262 @@ fn render_gui(
 
 }
 
-fn transform_pos(pos: &mut glm::Vec2, screen: &Screen) {
-    pos.x = pos.x - CANVAS_WIDTH as f32 / 2_f32;
-    pos.y = - pos.y + CANVAS_HEIGHT as f32 / 2_f32;
-    let scale = screen.get_scale();
-    let translationv = screen.get_pos().component_mul(
-        &glm::vec2(WINDOW_WIDTH as f32 / 2.0, WINDOW_HEIGHT as f32 / 2.0)
+struct View {
+    camera: Camera2D,
+    overlay: ScreenOverlay,
+    split: SplitView,
+    difference: DifferenceView,
+    visualizer: Visualizer,
+    recorder: Recorder,
+    inspector: PixelInspector,
+    canvas_size_input: [u16; 2],
+}
+
+impl View {
+
+    // Takes pan, zoom and rotation from a pane's camera.
+    fn set_camera(&mut self, camera: &Camera2D) {
+        self.camera = camera.with_viewport(
+            &glm::vec2(0.0, 0.0), &self.camera.get_viewport_size()
+        );
+    }
+
+    fn get_active_camera(&self) -> Camera2D {
+        self.split.pane_cameras(&self.camera)[self.split.get_active_pane()]
+    }
+}
+
+impl Default for View {
+    fn default() -> Self {
+        Self {
+            camera: Camera2D::new(WINDOW_WIDTH as f32, WINDOW_HEIGHT as f32),
+            overlay: ScreenOverlay::default(),
+            split: SplitView::default(),
+            difference: DifferenceView::default(),
+            visualizer: Visualizer::default(),
+            recorder: Recorder::default(),
+            inspector: PixelInspector::default(),
+            canvas_size_input: [DEFAULT_CANVAS_WIDTH, DEFAULT_CANVAS_HEIGHT],
+        }
+    }
+}
+
+struct Editor {
+    tool: Tool,
+    line_start: Option<glm::Vec2>,
+    snap_settings: SnapSettings,
+    line_table: LineTable,
+    line_style: LineStyle,
+    measurement: Measurement,
+    gizmo: Gizmo,
+    // How the group being edited in the GUI was before the edit.
+    group_edit: Option<scene_graph::Group>,
+}
+
+impl Default for Editor {
+    fn default() -> Self {
+        Self {
+            tool: Tool::Draw,
+            line_start: None,
+            snap_settings: SnapSettings::default(),
+            line_table: LineTable::default(),
+            line_style: LineStyle::default(),
+            measurement: Measurement::default(),
+            gizmo: Gizmo::default(),
+            group_edit: None,
+        }
+    }
+}
+
+impl Editor {
+
+    // Has to be called before undoing, so the pending edits are recorded in
+    // the state they were made in.
+    fn commit_edits(&mut self, scene: &mut Scene) {
+        self.line_table.commit_edit(scene);
+        commit_group_edit(scene, &mut self.group_edit);
+    }
+}
+
+struct FileSettings {
+    path: String,
+    curve_tolerance: f32,
+    dxf_pixels_per_unit: f32,
+    dxf_units: DxfUnits,
+    status: String,
+}
+
+impl Default for FileSettings {
+    fn default() -> Self {
+        Self {
+            path: String::new(),
+            curve_tolerance: svg::DEFAULT_TOLERANCE,
+            dxf_pixels_per_unit: 1.0,
+            dxf_units: DxfUnits::Unitless,
+            status: String::new(),
+        }
+    }
+}
+
+fn handle_draw_input(gui: &mut Gui, camera: &Camera2D, scene: &mut Scene, editor: &mut Editor) {
+
+    let cancel_requested = gui.consume_cursor_right_click_pos().is_some()
+        || gui.consume_key_press(glfw::Key::Escape, glfw::Modifiers::empty());
+
+    if cancel_requested {
+        editor.line_start = None;
+    }
+
+    if let Some(press_pos) = gui.consume_cursor_left_press_pos() {
+
+        if !scene.layers.is_editable(scene.layers.get_active()) {
+            editor.line_start = None;
+            return;
+        }
+
+        let press_pos = camera.screen_to_canvas(&press_pos);
+        let pos = snap_pos(&press_pos, gui, camera, scene, editor).pos;
+
+        match editor.line_start {
+
+            None => {
+                editor.line_start = Some(pos);
+            }
+
+            Some(start_pos) => {
+                scene.add_line(Line::new(start_pos, pos).with_style(editor.line_style.clone()));
+                editor.line_start = None;
+            }
+        }
+    }
+}
+
+fn handle_measure_input(gui: &mut Gui, camera: &Camera2D, scene: &Scene, editor: &mut Editor) {
+
+    let cancel_requested = gui.consume_cursor_right_click_pos().is_some()
+        || gui.consume_key_press(glfw::Key::Escape, glfw::Modifiers::empty());
+
+    if cancel_requested {
+        editor.measurement.cancel();
+    }
+
+    if let Some(press_pos) = gui.consume_cursor_left_press_pos() {
+        let press_pos = camera.screen_to_canvas(&press_pos);
+        let pos = snap_pos(&press_pos, gui, camera, scene, editor).pos;
+        editor.measurement.place_point(pos);
+    }
+}
+
+fn measurement_text(start: &glm::Vec2, end: &glm::Vec2) -> String {
+    let d = end - start;
+    format!(
+        "Distance: {:.2}  dx: {:.2}  dy: {:.2}  angle: {:.2}°",
+        glm::length(&d), d.x, d.y, measure::angle_degrees(&d)
+    )
+}
+
+fn paint_measurement(
+    painter: &egui::Painter,
+    pixels_per_point: f32,
+    camera: &Camera2D,
+    start: &glm::Vec2,
+    end: &glm::Vec2,
+) {
+
+    let to_window = |pos: &glm::Vec2| {
+        let pos = camera.canvas_to_screen(pos) / pixels_per_point;
+        egui::pos2(pos.x, pos.y)
+    };
+    let (start_pos, end_pos) = (to_window(start), to_window(end));
+    let color = egui::Color32::from_rgb(255, 220, 80);
+
+    painter.line_segment([start_pos, end_pos], (1.5, color));
+    painter.circle_filled(start_pos, 3.0, color);
+    painter.circle_filled(end_pos, 3.0, color);
+
+    let d = end - start;
+    painter.text(
+        start_pos + (end_pos - start_pos) * 0.5 + egui::vec2(6.0, -6.0),
+        egui::Align2::LEFT_BOTTOM,
+        format!("{:.2} ({:.2}°)", glm::length(&d), measure::angle_degrees(&d)),
+        egui::TextStyle::Body,
+        color,
     );
-    let scale_mat = glm::diagonal3x3(&glm::vec3(scale, scale, 1.0));
-    let translation_mat = glm::translate2d(&glm::Mat3::identity(), &translationv);
-    let transform_inv = glm::inverse(&(scale_mat * translation_mat));
-    let pos3 = glm::vec3(pos.x, pos.y, 1.0);
-    let result = transform_inv * pos3;
-    pos.x = result.x;
-    pos.y = result.y;
 }
 
-#[allow(dead_code)]
-fn normalize_pos(pos: &mut glm::Vec2) {
-    pos.x = (pos.x * 2_f32) / WINDOW_WIDTH as f32;
-    pos.y = (pos.y * 2_f32) / WINDOW_HEIGHT as f32;
+fn snap_cursor_pos(gui: &Gui, camera: &Camera2D, scene: &Scene, editor: &Editor) -> Snap {
+    let cursor_pos = camera.screen_to_canvas(&gui.get_cursor_pos());
+    snap_pos(&cursor_pos, gui, camera, scene, editor)
+}
+
+// Holding shift constrains the pending line to the angle step.
+fn snap_pos(
+    pos: &glm::Vec2,
+    gui: &Gui,
+    camera: &Camera2D,
+    scene: &Scene,
+    editor: &Editor,
+) -> Snap {
+
+    let shift_down = gui.is_key_pressed(glfw::Key::LeftShift)
+        || gui.is_key_pressed(glfw::Key::RightShift);
+
+    let anchor = match editor.tool {
+        Tool::Measure => editor.measurement.get_pending_start(),
+        _ => editor.line_start.as_ref(),
+    };
+    let constrain_from = if shift_down { anchor } else { None };
+
+    editor.snap_settings.snap(
+        pos,
+        &scene.visible_lines(),
+        SNAP_DISTANCE / camera.get_zoom(),
+        constrain_from,
+    )
+}
+
+fn paint_snap_indicator(
+    painter: &egui::Painter,
+    pixels_per_point: f32,
+    camera: &Camera2D,
+    kind: SnapKind,
+    pos: &glm::Vec2,
+) {
+
+    let window_pos = camera.canvas_to_screen(pos) / pixels_per_point;
+    let center = egui::pos2(window_pos.x, window_pos.y);
+    let stroke = egui::Stroke::new(1.5, egui::Color32::from_rgb(255, 140, 0));
+    let r = 5.0;
+
+    match kind {
+
+        SnapKind::None => {}
+
+        SnapKind::Pixel | SnapKind::Grid => {
+            painter.circle_stroke(center, 2.0, stroke);
+        }
+
+        SnapKind::Endpoint => {
+            painter.rect_stroke(
+                egui::Rect::from_center_size(center, egui::vec2(2.0 * r, 2.0 * r)), 0.0, stroke
+            );
+        }
+
+        SnapKind::Midpoint => {
+            let top = center + egui::vec2(0.0, -r);
+            let left = center + egui::vec2(-r, r);
+            let right = center + egui::vec2(r, r);
+            painter.line_segment([top, left], stroke);
+            painter.line_segment([left, right], stroke);
+            painter.line_segment([right, top], stroke);
+        }
+
+        SnapKind::Intersection => {
+            painter.line_segment([center + egui::vec2(-r, -r), center + egui::vec2(r, r)], stroke);
+            painter.line_segment([center + egui::vec2(-r, r), center + egui::vec2(r, -r)], stroke);
+        }
+
+        SnapKind::Angle => {
+            painter.circle_stroke(center, r, stroke);
+        }
+    }
+}
+
+// Everything drawn over the canvas besides the pixel overlays.
+fn paint_overlay(
+    painter: &egui::Painter,
+    pixels_per_point: f32,
+    camera: &Camera2D,
+    overlay: &ScreenOverlay,
+    lines: &[Line],
+) {
+
+    if overlay.ideal_lines {
+        paint_ideal_lines(painter, pixels_per_point, camera, lines);
+    }
+
+    if overlay.endpoint_labels {
+        rulers::paint_endpoint_labels(painter, camera, pixels_per_point, lines);
+    }
+
+    if overlay.rulers {
+        rulers::paint_rulers(painter, camera, pixels_per_point);
+    }
+}
+
+// Selected lines are drawn with their own style, so they are highlighted
+// over the pixels instead of in them, along with the handles.
+fn paint_selection_handles(
+    painter: &egui::Painter,
+    pixels_per_point: f32,
+    camera: &Camera2D,
+    line: &Line,
+) {
+
+    let stroke = egui::Stroke::new(1.5, egui::Color32::from_rgb(255, 200, 0));
+
+    let start = camera.canvas_to_screen(&line.start) / pixels_per_point;
+    let end = camera.canvas_to_screen(&line.end) / pixels_per_point;
+    painter.line_segment([egui::pos2(start.x, start.y), egui::pos2(end.x, end.y)], stroke);
+
+    for point in [&line.start, &line.end] {
+        let pos = camera.canvas_to_screen(point) / pixels_per_point;
+        painter.rect_stroke(
+            egui::Rect::from_center_size(egui::pos2(pos.x, pos.y), egui::vec2(8.0, 8.0)), 0.0, stroke
+        );
+    }
+}
+
+// The exact segments, to compare with the pixels the algorithm picked.
+fn paint_ideal_lines(painter: &egui::Painter, pixels_per_point: f32, camera: &Camera2D, lines: &[Line]) {
+
+    let stroke = egui::Stroke::new(1.0, egui::Color32::from_rgb(255, 0, 255));
+
+    for line in lines {
+        let start = camera.canvas_to_screen(&line.start) / pixels_per_point;
+        let end = camera.canvas_to_screen(&line.end) / pixels_per_point;
+        painter.line_segment([egui::pos2(start.x, start.y), egui::pos2(end.x, end.y)], stroke);
+    }
+}
+
+fn paint_pane_frame(painter: &egui::Painter, title: &str) {
+
+    let rect = painter.clip_rect();
+
+    painter.rect_stroke(rect.shrink(0.5), 0.0, (1.0, egui::Color32::from_gray(90)));
+    painter.text(
+        rect.right_top() + egui::vec2(-6.0, 4.0),
+        egui::Align2::RIGHT_TOP,
+        title,
+        egui::TextStyle::Body,
+        egui::Color32::from_rgb(255, 200, 0),
+    );
+}
+
+fn render_snap_gui(ui: &mut egui::Ui, snap_settings: &mut SnapSettings) {
+
+    ui.collapsing("Snapping", |ui| {
+
+        ui.checkbox(&mut snap_settings.pixel, "Pixel centers");
+
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut snap_settings.grid, "Grid");
+            ui.add(
+                egui::DragValue::new(&mut snap_settings.grid_size)
+                    .speed(0.5)
+                    .clamp_range(1.0..=1000.0)
+            );
+        });
+
+        ui.checkbox(&mut snap_settings.endpoints, "Endpoints");
+        ui.checkbox(&mut snap_settings.midpoints, "Midpoints");
+        ui.checkbox(&mut snap_settings.intersections, "Intersections");
+
+        ui.horizontal(|ui| {
+            ui.label("Shift angle step:");
+            if ui.radio(snap_settings.angle_step == AngleStep::Deg15, "15°").clicked() {
+                snap_settings.angle_step = AngleStep::Deg15;
+            }
+            if ui.radio(snap_settings.angle_step == AngleStep::Deg45, "45°").clicked() {
+                snap_settings.angle_step = AngleStep::Deg45;
+            }
+        });
+    });
+}
+
+fn handle_select_input(gui: &mut Gui, camera: &Camera2D, scene: &mut Scene) {
+
+    if let Some(press_pos) = gui.consume_cursor_left_press_pos() {
+        let press_pos = camera.screen_to_canvas(&press_pos);
+        scene.selection.press(
+            &press_pos,
+            &scene.world_lines(),
+            &scene.lines,
+            &scene.layers,
+            HIT_DISTANCE / camera.get_zoom(),
+        );
+    }
+
+    if scene.selection.is_dragging() {
+
+        if gui.is_left_button_down() {
+            let cursor_pos = camera.screen_to_canvas(&gui.get_cursor_pos());
+            if let Some(index) = scene.selection.get_selected() {
+                let to_local = scene_graph::inverse_or_identity(
+                    &scene.graph.world_matrix(scene.lines[index].group)
+                );
+                scene.selection.drag(&cursor_pos, &mut scene.lines, &to_local);
+            }
+        }
+        else if let Some(command) = scene.selection.release(&scene.lines) {
+            scene.execute(command);
+        }
+    }
+
+    if let Some(index) = scene.selection.get_selected() {
+        if gui.consume_key_press(glfw::Key::Delete, glfw::Modifiers::empty()) {
+            scene.delete_line(index);
+        }
+        else if gui.consume_key_press(glfw::Key::Escape, glfw::Modifiers::empty()) {
+            scene.selection.select(None);
+        }
+    }
+}
+
+fn handle_transform_input(gui: &mut Gui, camera: &Camera2D, scene: &mut Scene, gizmo: &mut Gizmo) {
+
+    if let Some(press_pos) = gui.consume_cursor_left_press_pos() {
+        gizmo.press(&camera.screen_to_canvas(&press_pos), &scene.graph);
+    }
+
+    if gizmo.is_dragging() {
+        if gui.is_left_button_down() {
+            let cursor_pos = camera.screen_to_canvas(&gui.get_cursor_pos());
+            gizmo.drag(&cursor_pos, &mut scene.graph);
+        }
+        else if let Some(command) = gizmo.release(&scene.graph) {
+            scene.execute(command);
+        }
+    }
+
+    if gui.consume_key_press(glfw::Key::Escape, glfw::Modifiers::empty()) {
+        scene.graph.select(None);
+    }
+}
+
+// The origin of the group and its axes, which show the scaling, shearing
+// and rotation of its lines.
+fn paint_gizmo(
+    painter: &egui::Painter,
+    pixels_per_point: f32,
+    camera: &Camera2D,
+    graph: &scene_graph::SceneGraph,
+    group: u32,
+    mode: GizmoMode,
+) {
+
+    let (origin, x_axis, y_axis) = match gizmo::gizmo_axes(graph, group) {
+        Some(axes) => axes,
+        None => return,
+    };
+
+    let to_window = |pos: &glm::Vec2| {
+        let pos = camera.canvas_to_screen(pos) / pixels_per_point;
+        egui::pos2(pos.x, pos.y)
+    };
+
+    // The axes keep their length on screen, their ratio shows the scaling.
+    let arm = GIZMO_SIZE / camera.get_zoom();
+    let longest = glm::length(&x_axis).max(glm::length(&y_axis)).max(f32::EPSILON);
+    let origin_pos = to_window(&origin);
+
+    for (axis, color) in [
+        (x_axis, egui::Color32::from_rgb(230, 70, 70)),
+        (y_axis, egui::Color32::from_rgb(70, 200, 70)),
+    ] {
+        let end_pos = to_window(&(origin + axis / longest * arm));
+        painter.line_segment([origin_pos, end_pos], (2.0, color));
+        painter.rect_filled(egui::Rect::from_center_size(end_pos, egui::vec2(6.0, 6.0)), 0.0, color);
+    }
+
+    let color = egui::Color32::from_rgb(255, 200, 0);
+    if mode == GizmoMode::Rotate {
+        painter.circle_stroke(origin_pos, GIZMO_SIZE / pixels_per_point, (1.0, color));
+    }
+    painter.circle_filled(origin_pos, 3.0, color);
+    painter.text(
+        origin_pos + egui::vec2(6.0, 6.0),
+        egui::Align2::LEFT_TOP,
+        mode.name(),
+        egui::TextStyle::Body,
+        color,
+    );
+}
+
+// Groups are listed as a tree, the selected one is edited below it. Its
+// edits are applied live and recorded as a single command once no value is
+// being dragged or typed, `group_edit` keeps how it was until then.
+fn render_groups_gui(
+    ui: &mut egui::Ui,
+    scene: &mut Scene,
+    group_edit: &mut Option<scene_graph::Group>,
+) {
+
+    let mut editing = false;
+
+    ui.collapsing("Groups", |ui| {
+
+        let mut selected = scene.graph.get_selected();
+
+        for (depth, id) in scene.graph.get_tree() {
+            let name = &scene.graph.get_group(id).unwrap().name;
+            ui.horizontal(|ui| {
+                ui.add_space(depth as f32 * 16.0);
+                ui.selectable_value(&mut selected, Some(id), name);
+            });
+        }
+
+        scene.graph.select(selected);
+
+        ui.horizontal(|ui| {
+            if ui.button("Add group").clicked() {
+                commit_group_edit(scene, group_edit);
+                scene.add_group(None);
+            }
+            if ui.add(egui::Button::new("Add child").enabled(selected.is_some())).clicked() {
+                commit_group_edit(scene, group_edit);
+                scene.add_group(selected);
+            }
+            if let Some(id) = selected {
+                let empty = !scene.graph.has_children(id)
+                    && !scene.lines.iter().any(|line| line.group == Some(id));
+                if ui.add(egui::Button::new("Remove").enabled(empty))
+                    .on_hover_text("Only groups without lines or groups in them can be removed")
+                    .clicked() {
+                    commit_group_edit(scene, group_edit);
+                    scene.remove_group(id);
+                }
+            }
+        });
+
+        let id = match scene.graph.get_selected() {
+            Some(id) => id,
+            None => return,
+        };
+
+        ui.separator();
+
+        let mut group = scene.graph.get_group(id).unwrap().clone();
+        let parent_name = |parent: Option<u32>| match parent.and_then(|parent| scene.graph.get_group(parent)) {
+            Some(group) => group.name.clone(),
+            None => "None".to_string(),
+        };
+
+        egui::ComboBox::from_label("Parent")
+            .selected_text(parent_name(group.parent))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut group.parent, None, "None");
+                for option in scene.graph.get_groups() {
+                    // Its own descendants can not be its parent.
+                    if !scene.graph.is_ancestor(id, option.id) {
+                        ui.selectable_value(&mut group.parent, Some(option.id), &option.name);
+                    }
+                }
+            });
+
+        let mut responses = Vec::new();
+
+        ui.horizontal(|ui| {
+            ui.label("Name:");
+            responses.push(ui.text_edit_singleline(&mut group.name));
+        });
+
+        let transform = &mut group.transform;
+
+        egui::Grid::new("group_transform").show(ui, |ui| {
+
+            for (label, value) in [
+                ("Translation", &mut transform.translation),
+                ("Scale", &mut transform.scale),
+                ("Shear", &mut transform.shear),
+                ("Pivot", &mut transform.pivot),
+            ] {
+                ui.label(label);
+                responses.push(ui.add(egui::DragValue::new(&mut value.x).speed(0.05).max_decimals(3)));
+                responses.push(ui.add(egui::DragValue::new(&mut value.y).speed(0.05).max_decimals(3)));
+                ui.end_row();
+            }
+
+            ui.label("Rotation");
+            responses.push(ui.add(egui::DragValue::new(&mut transform.rotation).speed(0.5).suffix("°")));
+            ui.end_row();
+        });
+
+        if ui.button("Reset transform").clicked() {
+            *transform = scene_graph::Transform::default();
+        }
+
+        editing = responses.iter().any(|response| response.dragged() || response.has_focus());
+
+        if scene.graph.get_group(id) != Some(&group) {
+            if !matches!(group_edit, Some(before) if before.id == id) {
+                commit_group_edit(scene, group_edit);
+                *group_edit = scene.graph.get_group(id).cloned();
+            }
+            scene.graph.set_group(group.clone());
+        }
+
+        // The composition, applied right to left.
+        ui.label("T(translation + pivot) R Shx Shy S T(-pivot) =");
+        let matrix = group.transform.matrix();
+        egui::Grid::new("group_matrix").show(ui, |ui| {
+            for row in 0..3 {
+                for column in 0..3 {
+                    ui.label(format!("{:.3}", matrix[(row, column)]));
+                }
+                ui.end_row();
+            }
+        });
+    });
+
+    if !editing {
+        commit_group_edit(scene, group_edit);
+    }
+}
+
+fn commit_group_edit(scene: &mut Scene, group_edit: &mut Option<scene_graph::Group>) {
+    if let Some(before) = group_edit.take() {
+        scene.record_group_edit(before);
+    }
+}
+
+// Pending edits are recorded first, so they are the ones undone.
+fn render_history_gui(ui: &mut egui::Ui, scene: &mut Scene, editor: &mut Editor) {
+
+    ui.collapsing("History", |ui| {
+
+        ui.horizontal(|ui| {
+
+            if ui.button("Undo (Ctrl+Z)").clicked() {
+                editor.commit_edits(scene);
+                scene.undo();
+            }
+
+            if ui.button("Redo (Ctrl+Shift+Z)").clicked() {
+                editor.commit_edits(scene);
+                scene.redo();
+            }
+        });
+
+        let history = &mut scene.history;
+        let mut max_size = history.get_max_size();
+        ui.horizontal(|ui| {
+            ui.label("Max size:");
+            ui.add(egui::DragValue::new(&mut max_size).clamp_range(1..=10000));
+        });
+        if max_size != history.get_max_size() {
+            history.set_max_size(max_size);
+        }
+
+        let mut go_to = None;
+
+        egui::ScrollArea::from_max_height(150.0).show(ui, |ui| {
+
+            if ui.selectable_label(history.get_undo_commands().next().is_none(), "Initial state")
+                .clicked()
+            {
+                go_to = Some(0);
+            }
+
+            let undo_count = history.get_undo_commands().count();
+
+            for (i, command) in history.get_undo_commands().enumerate() {
+                if ui.selectable_label(i + 1 == undo_count, command.description()).clicked() {
+                    go_to = Some(i + 1);
+                }
+            }
+
+            for (i, command) in history.get_redo_commands().enumerate() {
+                let label = egui::Label::new(command.description()).weak();
+                if ui.add(label.sense(egui::Sense::click())).clicked() {
+                    go_to = Some(undo_count + i + 1);
+                }
+            }
+        });
+
+        if let Some(count) = go_to {
+            editor.commit_edits(scene);
+            scene.go_to(count);
+        }
+    });
+}
+
+fn render_file_gui(
+    ui: &mut egui::Ui,
+    scene: &mut Scene,
+    file_settings: &mut FileSettings,
+    canvas_size: (u16, u16),
+) {
+
+    ui.horizontal(|ui| {
+        ui.label("File:");
+        ui.text_edit_singleline(&mut file_settings.path);
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("Curve tolerance (px):");
+        ui.add(
+            egui::DragValue::new(&mut file_settings.curve_tolerance)
+                .speed(0.01)
+                .clamp_range(0.01..=10.0)
+        );
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("DXF pixels per");
+        let units = &mut file_settings.dxf_units;
+        egui::ComboBox::from_id_source("dxf_units")
+            .selected_text(units.name())
+            .show_ui(ui, |ui| {
+                for option in DxfUnits::ALL {
+                    ui.selectable_value(units, option, option.name());
+                }
+            });
+        ui.add(
+            egui::DragValue::new(&mut file_settings.dxf_pixels_per_unit)
+                .speed(0.1)
+                .clamp_range(0.001..=1000.0)
+        );
+    })
+    .response
+    .on_hover_text("Drawings in other units are converted, unitless ones are taken as they are");
+
+    ui.horizontal(|ui| {
+
+        if ui.button("Import SVG").clicked() {
+            import_svg_file(scene, file_settings, canvas_size);
+        }
+
+        if ui.button("Import DXF").clicked() {
+            import_dxf_file(scene, file_settings);
+        }
+
+        if ui.button("Export DXF").clicked() {
+            file_settings.status = match dxf::export_dxf(
+                &file_settings.path,
+                &scene.world_lines(),
+                &scene.layers,
+                file_settings.dxf_pixels_per_unit,
+            ) {
+                Ok(()) => format!("Exported {} lines", scene.lines.len()),
+                Err(err) => err,
+            };
+        }
+    });
+
+    if !file_settings.status.is_empty() {
+        ui.label(&file_settings.status);
+    }
+}
+
+fn import_svg_file(scene: &mut Scene, file_settings: &mut FileSettings, canvas_size: (u16, u16)) {
+
+    if !scene.layers.is_editable(scene.layers.get_active()) {
+        file_settings.status = "The active layer is hidden or locked".to_string();
+        return;
+    }
+
+    match svg::import_svg(
+        &file_settings.path,
+        file_settings.curve_tolerance,
+        canvas_size,
+    ) {
+        Ok(imported_lines) => {
+            file_settings.status = format!(
+                "Imported {} lines", imported_lines.len()
+            );
+            scene.add_lines(imported_lines);
+        }
+        Err(err) => {
+            file_settings.status = err;
+        }
+    }
+}
+
+fn import_dxf_file(scene: &mut Scene, file_settings: &mut FileSettings) {
+
+    match dxf::import_dxf(
+        &file_settings.path,
+        file_settings.dxf_pixels_per_unit,
+        file_settings.dxf_units,
+        file_settings.curve_tolerance,
+    ) {
+        Ok(import) => {
+
+            // DXF layers go to the layers with their name, none of them can
+            // be hidden or locked.
+            let blocked_layer = import.layers
+                .keys()
+                .find(|name| matches!(scene.layers.find(name), Some(id) if !scene.layers.is_editable(id)));
+            if let Some(name) = blocked_layer {
+                file_settings.status = format!("Layer {} is hidden or locked", name);
+                return;
+            }
+
+            file_settings.status = import.summary();
+            scene.add_lines_to_named_layers(import.lines);
+        }
+        Err(err) => {
+            file_settings.status = err;
+        }
+    }
+}
+
+fn resize_window(gui: &Gui, screen: &mut Screen, view: &mut View, width: i32, height: i32) {
+
+    // Minimized windows have no size.
+    if width <= 0 || height <= 0 {
+        return;
+    }
+
+    // The screen takes framebuffer pixels and the cameras window ones, like
+    // the cursor. They differ on HiDPI displays, the screen scales the
+    // viewports when drawing (see Gui::get_framebuffer_scale).
+    screen.set_size(width as u16, height as u16);
+
+    let (window_width, window_height) = gui.get_window_size();
+    view.camera.set_viewport_size(&glm::vec2(window_width as f32, window_height as f32));
+}
+
+fn render_split_view_gui(ui: &mut egui::Ui, split: &mut SplitView) {
+
+    ui.collapsing("Compare algorithms", |ui| {
+
+        ui.horizontal(|ui| {
+            for mode in SplitMode::ALL {
+                ui.selectable_value(&mut split.mode, mode, mode.name());
+            }
+        });
+
+        ui.label("The first view shows the selected algorithm.");
+
+        for (i, algorithem) in split.algorithems.iter_mut().enumerate() {
+
+            if i + 1 >= split.mode.pane_count() {
+                break;
+            }
+
+            egui::ComboBox::from_label(format!("View {}", i + 2))
+                .selected_text(algorithem.name())
+                .show_ui(ui, |ui| {
+                    for option in LineAlgorithem::ALL {
+                        ui.selectable_value(algorithem, option, option.name());
+                    }
+                });
+        }
+    });
+}
+
+enum LayerAction {
+    Raise(usize),
+    Lower(usize),
+    Remove(u32),
+}
+
+// Listed from the top layer down, like they are drawn.
+fn render_layers_gui(ui: &mut egui::Ui, scene: &mut Scene) {
+
+    ui.collapsing("Layers", |ui| {
+
+        let mut active = scene.layers.get_active();
+        let mut action = None;
+
+        let line_counts: Vec<usize> = scene.layers
+            .get_layers()
+            .iter()
+            .map(|layer| scene.lines.iter().filter(|line| line.layer == layer.id).count())
+            .collect();
+        let layer_count = line_counts.len();
+
+        egui::Grid::new("layers").striped(true).show(ui, |ui| {
+
+            ui.label("");
+            ui.label("Name");
+            ui.label("Lines");
+            ui.label("Show");
+            ui.label("Lock");
+            ui.label("Opacity");
+            ui.label("Algorithm");
+            ui.end_row();
+
+            for (i, layer) in scene.layers.get_layers_mut().iter_mut().enumerate().rev() {
+
+                ui.radio_value(&mut active, layer.id, "")
+                    .on_hover_text("New lines go to the active layer");
+                ui.add(egui::TextEdit::singleline(&mut layer.name).desired_width(80.0));
+                ui.label(line_counts[i].to_string());
+                ui.checkbox(&mut layer.visible, "");
+                ui.checkbox(&mut layer.locked, "");
+                ui.add(
+                    egui::DragValue::new(&mut layer.opacity)
+                        .speed(0.01)
+                        .clamp_range(0.0..=1.0)
+                        .max_decimals(2)
+                );
+
+                egui::ComboBox::from_id_source(("layer_algorithem", layer.id))
+                    .selected_text(layer.algorithem.map_or("View's", |algorithem| algorithem.short_name()))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut layer.algorithem, None, "View's");
+                        for option in LineAlgorithem::ALL {
+                            ui.selectable_value(&mut layer.algorithem, Some(option), option.name());
+                        }
+                    });
+
+                ui.horizontal(|ui| {
+                    if ui.add(egui::Button::new("^").small().enabled(i + 1 < layer_count)).clicked() {
+                        action = Some(LayerAction::Raise(i));
+                    }
+                    if ui.add(egui::Button::new("v").small().enabled(i > 0)).clicked() {
+                        action = Some(LayerAction::Lower(i));
+                    }
+                    let removable = layer_count > 1 && line_counts[i] == 0;
+                    if ui.add(egui::Button::new("x").small().enabled(removable))
+                        .on_hover_text("Only empty layers can be removed")
+                        .clicked() {
+                        action = Some(LayerAction::Remove(layer.id));
+                    }
+                });
+
+                ui.end_row();
+            }
+        });
+
+        scene.layers.set_active(active);
+
+        match action {
+            Some(LayerAction::Raise(i)) => scene.layers.raise(i),
+            Some(LayerAction::Lower(i)) => scene.layers.lower(i),
+            Some(LayerAction::Remove(id)) => scene.remove_layer(id),
+            None => {}
+        }
+
+        if ui.button("Add layer").clicked() {
+            scene.add_layer();
+        }
+
+        if !scene.layers.is_editable(scene.layers.get_active()) {
+            ui.label("The active layer is hidden or locked, it can not be drawn on.");
+        }
+
+        // Hiding or locking a layer drops its selected line.
+        scene.validate_selection();
+    });
+}
+
+fn render_difference_gui(ui: &mut egui::Ui, difference: &mut DifferenceView) {
+
+    ui.collapsing("Difference map", |ui| {
+
+        ui.checkbox(&mut difference.enabled, "Show in the first view");
+
+        for (label, algorithem) in [
+            ("A", &mut difference.algorithem_a),
+            ("B", &mut difference.algorithem_b),
+        ] {
+            egui::ComboBox::from_label(label)
+                .selected_text(algorithem.name())
+                .show_ui(ui, |ui| {
+                    for option in LineAlgorithem::ALL {
+                        ui.selectable_value(algorithem, option, option.name());
+                    }
+                });
+        }
+
+        ui.horizontal(|ui| {
+            for (color, label) in [
+                (&difference::ONLY_A_COLOR, "Only A"),
+                (&difference::ONLY_B_COLOR, "Only B"),
+                (&difference::BOTH_COLOR, "Both"),
+            ] {
+                ui.colored_label(egui::Color32::from_rgb(color.r, color.g, color.b), label);
+            }
+        });
+
+        let map = match difference.get_map() {
+            Some(map) => map,
+            None => return,
+        };
+
+        egui::ScrollArea::from_max_height(150.0).show(ui, |ui| {
+            egui::Grid::new("difference_counts").striped(true).show(ui, |ui| {
+
+                ui.label("Line");
+                ui.label("Only A");
+                ui.label("Only B");
+                ui.label("Both");
+                ui.end_row();
+
+                for (i, counts) in map.per_line.iter().enumerate() {
+                    ui.label(i.to_string());
+                    ui.label(counts.only_a.to_string());
+                    ui.label(counts.only_b.to_string());
+                    ui.label(counts.both.to_string());
+                    ui.end_row();
+                }
+
+                ui.label("Total");
+                ui.label(map.total.only_a.to_string());
+                ui.label(map.total.only_b.to_string());
+                ui.label(map.total.both.to_string());
+                ui.end_row();
+            });
+        });
+    });
+}
+
+fn render_visualizer_gui(ui: &mut egui::Ui, visualizer: &mut Visualizer, canvas_size: (u16, u16)) {
+
+    ui.collapsing("Step by step", |ui| {
+
+        ui.checkbox(&mut visualizer.enabled, "Show the selected line in the first view");
+
+        if !visualizer.is_active() {
+            ui.label("Select a line to trace it.");
+            return;
+        }
+
+        ui.horizontal(|ui| {
+            if ui.button("|<").clicked() {
+                visualizer.set_step(0);
+            }
+            if ui.button("<").clicked() {
+                visualizer.step_back();
+            }
+            let play_label = if visualizer.is_playing() { "Pause" } else { "Play" };
+            if ui.button(play_label).clicked() {
+                visualizer.toggle_playing();
+            }
+            if ui.button(">").clicked() {
+                visualizer.step_forward();
+            }
+            if ui.button(">|").clicked() {
+                visualizer.set_step(usize::MAX);
+            }
+        });
+
+        let mut step = visualizer.get_step();
+        let last_step = visualizer.get_trace().len() - 1;
+        ui.add(egui::Slider::new(&mut step, 0..=last_step).text("Step"));
+        if step != visualizer.get_step() {
+            visualizer.set_step(step);
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("Speed:");
+            ui.add(
+                egui::DragValue::new(&mut visualizer.steps_per_second)
+                    .speed(0.1)
+                    .clamp_range(0.5..=120.0)
+                    .suffix(" steps/s")
+            );
+        });
+
+        ui.label("Space plays or pauses, the arrow keys step.");
+
+        let (canvas_width, canvas_height) = canvas_size;
+        let mut clicked_step = None;
+
+        // Pixels are shown in canvas coordinates, the ideal position and the
+        // decision variable as the algorithm computed them.
+        egui::ScrollArea::from_max_height(200.0).show(ui, |ui| {
+            egui::Grid::new("visualizer_trace").striped(true).show(ui, |ui| {
+
+                ui.label("Step");
+                ui.label("Pixel");
+                ui.label("x");
+                ui.label("y");
+                ui.label("e");
+                ui.end_row();
+
+                for (i, trace_step) in visualizer.get_trace().iter().enumerate() {
+
+                    let text = i.to_string();
+                    if ui.selectable_label(i == visualizer.get_step(), text).clicked() {
+                        clicked_step = Some(i);
+                    }
+                    ui.label(format!(
+                        "({}, {})",
+                        trace_step.pixel.x as i32 - (canvas_width / 2) as i32,
+                        trace_step.pixel.y as i32 - (canvas_height / 2) as i32,
+                    ));
+                    ui.label(format!("{:.2}", trace_step.pos.x));
+                    ui.label(format!("{:.2}", trace_step.pos.y));
+                    ui.label(match trace_step.error {
+                        Some(error) => error.to_string(),
+                        None => "-".to_string(),
+                    });
+                    ui.end_row();
+                }
+            });
+        });
+
+        if let Some(step) = clicked_step {
+            visualizer.set_step(step);
+        }
+    });
+}
+
+fn render_pixel_inspection(ui: &mut egui::Ui, inspection: &PixelInspection) {
+
+    let color = &inspection.color;
+
+    ui.horizontal(|ui| {
+        ui.label(format!("Pixel: ({}, {})", inspection.pixel.x, inspection.pixel.y));
+        let (swatch, _) = ui.allocate_exact_size(egui::vec2(12.0, 12.0), egui::Sense::hover());
+        ui.painter().rect_filled(swatch, 2.0, egui::Color32::from_rgb(color.r, color.g, color.b));
+        ui.label(format!("RGBA: ({}, {}, {}, {})", color.r, color.g, color.b, color.a));
+    });
+
+    if inspection.hits.is_empty() {
+        ui.label(format!("No line drawn here by {}", inspection.algorithem.name()));
+        return;
+    }
+
+    egui::Grid::new("pixel_inspection").striped(true).show(ui, |ui| {
+
+        ui.label("Line");
+        ui.label("Distance");
+        ui.label(format!("{} step", inspection.algorithem.name()));
+        ui.end_row();
+
+        for hit in &inspection.hits {
+            let steps: Vec<String> = hit.steps.iter().map(|step| step.to_string()).collect();
+            ui.label(hit.line.to_string());
+            ui.label(format!("{:.3}", hit.distance));
+            if hit.algorithem == inspection.algorithem {
+                ui.label(steps.join(", "));
+            } else {
+                ui.label(format!("{} ({})", steps.join(", "), hit.algorithem.short_name()));
+            }
+            ui.end_row();
+        }
+    });
+}
+
+fn render_recording_gui(ui: &mut egui::Ui, view: &mut View, canvas_size: (u16, u16)) {
+
+    ui.collapsing("Recording", |ui| {
+
+        let recorder = &mut view.recorder;
+
+        egui::ComboBox::from_label("Format")
+            .selected_text(recorder.format.name())
+            .show_ui(ui, |ui| {
+                for option in RecordingFormat::ALL {
+                    ui.selectable_value(&mut recorder.format, option, option.name());
+                }
+            });
+
+        ui.horizontal(|ui| {
+            ui.label("File:");
+            ui.text_edit_singleline(&mut recorder.path);
+        });
+
+        ui.add(egui::Slider::new(&mut recorder.scale, 1..=16).text("Pixel size"));
+
+        if recorder.is_recording() {
+            ui.label(format!("Recording the first view, {} frames", recorder.get_frame_count()));
+            if ui.button("Stop and save").clicked() {
+                recorder.stop();
+            }
+        }
+        else if ui.button("Record the first view").clicked() {
+            recorder.start(canvas_size);
+        }
+
+        // Frames straight from the traced algorithm, one per step.
+        let can_export_steps = view.visualizer.is_active() && !recorder.is_recording();
+        let export_steps = egui::Button::new("Export step by step").enabled(can_export_steps);
+        if ui.add(export_steps).clicked() {
+            let frames = recording::step_frames(&view.visualizer, canvas_size);
+            recorder.status = match recorder.save(&frames, canvas_size) {
+                Ok(message) => message,
+                Err(err) => err,
+            };
+        }
+
+        if !recorder.status.is_empty() {
+            ui.label(&recorder.status);
+        }
+    });
+}
+
+// Canvas sizes are kept even so the center pixel is the same for all the
+// algorithms.
+fn render_canvas_size_gui(ui: &mut egui::Ui, view: &mut View, lines_renderer: &mut LinesRenderer) {
+
+    let mut new_size = None;
+
+    ui.collapsing("Canvas", |ui| {
+
+        ui.horizontal(|ui| {
+            let [width, height] = &mut view.canvas_size_input;
+            ui.add(egui::DragValue::new(width).clamp_range(2..=4096));
+            ui.label("x");
+            ui.add(egui::DragValue::new(height).clamp_range(2..=4096));
+            if ui.button("Apply").clicked() {
+                new_size = Some((*width & !1, *height & !1));
+            }
+        });
+
+        ui.horizontal(|ui| {
+            for (width, height) in [(64, 48), (160, 120), (320, 240), (800, 600)] {
+                if ui.button(format!("{}x{}", width, height)).clicked() {
+                    new_size = Some((width, height));
+                }
+            }
+        });
+
+        let mut transparent = lines_renderer.is_background_transparent();
+        if ui.checkbox(&mut transparent, "Transparent background").changed() {
+            lines_renderer.set_background_transparent(transparent);
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("Raster operation");
+            let mut raster_op = lines_renderer.get_raster_op();
+            edit_raster_op(ui, "canvas_raster_op".to_string(), &mut raster_op);
+            if raster_op != lines_renderer.get_raster_op() {
+                lines_renderer.set_raster_op(raster_op);
+            }
+        })
+        .response
+        .on_hover_text("Used by every line instead of its own, on the CPU");
+
+        ui.horizontal(|ui| {
+            let mut persistent = lines_renderer.is_persistent();
+            if ui.checkbox(&mut persistent, "Persistent").changed() {
+                lines_renderer.set_persistent(persistent);
+            }
+            if ui.add(egui::Button::new("Clear").enabled(persistent)).clicked() {
+                lines_renderer.clear_persistent_canvases();
+            }
+        })
+        .response
+        .on_hover_text(
+            "Only new lines are drawn, over the last frame. Edits and deletions stay \
+             on the canvas until it is cleared, and pixels shared by lines are drawn twice."
+        );
+    });
+
+    if let Some((width, height)) = new_size {
+        view.canvas_size_input = [width, height];
+        lines_renderer.set_canvas_size(width, height);
+        let mut camera = view.get_active_camera();
+        camera.reset(&glm::vec2(width as f32, height as f32));
+        view.set_camera(&camera);
+    }
+}
+
+fn zoom_to_fit(camera: &mut Camera2D, lines: &[Line], canvas_size: &glm::Vec2) {
+
+    if lines.is_empty() {
+        camera.reset(canvas_size);
+        return;
+    }
+
+    let mut min = glm::vec2(f32::MAX, f32::MAX);
+    let mut max = glm::vec2(f32::MIN, f32::MIN);
+
+    for line in lines {
+        for point in [&line.start, &line.end] {
+            min = glm::min2(&min, point);
+            max = glm::max2(&max, point);
+        }
+    }
+
+    camera.fit(&min, &max);
 }
 
 fn get_move_deltas(gui: &Gui, dt: &Duration, move_speed: &glm::Vec2) -> glm::Vec2 {
@@ -221,10 +1787,10 @@ fn get_move_deltas(gui: &Gui, dt: &Duration, move_speed: &glm::Vec2) -> glm::Vec
     }
 
     if gui.is_key_pressed(glfw::Key::W) {
-        move_direction.y = -1.0;
+        move_direction.y = 1.0;
     }
     else if gui.is_key_pressed(glfw::Key::S) {
-        move_direction.y = 1.0;
+        move_direction.y = -1.0;
     }
 
     move_speed.component_mul(&move_direction) * dt.as_secs_f32()
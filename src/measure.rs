@@ -0,0 +1,88 @@
+use nalgebra_glm as glm;
+
+use crate::{
+    line::Line,
+    lines_renderer::{LinesRenderer, LineAlgorithem, LineKind},
+};
+
+pub struct LineMeasurements {
+    pub length: f32,
+    // In degrees, counterclockwise from +x in [0, 360).
+    pub angle: f32,
+    pub m: f32,
+    pub b: f32,
+    pub kind: LineKind,
+    pub octant: u8,
+    // One per LineAlgorithem::ALL, only the pixels inside the canvas.
+    pub pixel_counts: [usize; 4],
+}
+
+pub fn measure_line(line: &Line, tex_width: u16, tex_height: u16) -> LineMeasurements {
+
+    let (m, b, kind) = LinesRenderer::comput_m_b(line);
+    let angle = angle_degrees(&(line.end - line.start));
+
+    let mut pixel_counts = [0; 4];
+    for (count, algorithem) in pixel_counts.iter_mut().zip(LineAlgorithem::ALL) {
+        *count = LinesRenderer::rasterize(&algorithem, line, tex_width, tex_height).len();
+    }
+
+    LineMeasurements {
+        length: glm::distance(&line.start, &line.end),
+        angle,
+        m,
+        b,
+        kind,
+        octant: octant(angle),
+        pixel_counts,
+    }
+}
+
+pub fn angle_degrees(direction: &glm::Vec2) -> f32 {
+    direction.y.atan2(direction.x).to_degrees().rem_euclid(360.0)
+}
+
+// Octants are numbered 1 to 8 counterclockwise, the first one goes from
+// +x to the diagonal.
+pub fn octant(angle_degrees: f32) -> u8 {
+    (angle_degrees / 45.0).floor().clamp(0.0, 7.0) as u8 + 1
+}
+
+// Two clicks on the canvas measure the distance between them. The second
+// point follows the cursor until it is placed.
+#[derive(Default)]
+pub struct Measurement {
+    start: Option<glm::Vec2>,
+    end: Option<glm::Vec2>,
+}
+
+impl Measurement {
+
+    // A click after a finished measurement starts a new one.
+    pub fn place_point(&mut self, pos: glm::Vec2) {
+        match (self.start, self.end) {
+            (Some(_), None) => self.end = Some(pos),
+            _ => {
+                self.start = Some(pos);
+                self.end = None;
+            }
+        }
+    }
+
+    pub fn cancel(&mut self) {
+        self.start = None;
+        self.end = None;
+    }
+
+    // The start of a measurement still being placed.
+    pub fn get_pending_start(&self) -> Option<&glm::Vec2> {
+        match self.end {
+            None => self.start.as_ref(),
+            Some(_) => None,
+        }
+    }
+
+    pub fn get_segment(&self, cursor_pos: &glm::Vec2) -> Option<(glm::Vec2, glm::Vec2)> {
+        self.start.map(|start| (start, self.end.unwrap_or(*cursor_pos)))
+    }
+}
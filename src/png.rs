@@ -0,0 +1,156 @@
+use crate::common::ColorU8;
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'];
+const MAX_STORED_BLOCK_LEN: usize = 0xFFFF;
+
+// Rows go from top to bottom. The image data is stored without
+// compression, canvases are small enough that it does not matter.
+pub fn encode_png(pixels: &[ColorU8], width: u16, height: u16) -> Vec<u8> {
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(width as u32).to_be_bytes());
+    header.extend_from_slice(&(height as u32).to_be_bytes());
+    // 8 bits RGBA, default compression, filter and interlace methods.
+    header.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+    let mut scanlines = Vec::with_capacity((width as usize * 4 + 1) * height as usize);
+    for row in pixels.chunks(width as usize) {
+        scanlines.push(0);
+        for color in row {
+            scanlines.extend_from_slice(&[color.r, color.g, color.b, color.a]);
+        }
+    }
+
+    let mut png = SIGNATURE.to_vec();
+    write_chunk(&mut png, b"IHDR", &header);
+    write_chunk(&mut png, b"IDAT", &zlib_stored(&scanlines));
+    write_chunk(&mut png, b"IEND", &[]);
+
+    png
+}
+
+fn write_chunk(png: &mut Vec<u8>, chunk_type: &[u8; 4], data: &[u8]) {
+
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+
+    let start = png.len();
+    png.extend_from_slice(chunk_type);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+// A zlib stream made of deflate stored blocks.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+
+    let mut stream = vec![0x78, 0x01];
+
+    let mut blocks = data.chunks(MAX_STORED_BLOCK_LEN).peekable();
+    while let Some(block) = blocks.next() {
+        stream.push(blocks.peek().is_none() as u8);
+        stream.extend_from_slice(&(block.len() as u16).to_le_bytes());
+        stream.extend_from_slice(&(!(block.len() as u16)).to_le_bytes());
+        stream.extend_from_slice(block);
+    }
+
+    stream.extend_from_slice(&adler32(data).to_be_bytes());
+
+    stream
+}
+
+fn crc32(data: &[u8]) -> u32 {
+
+    let mut crc = 0xFFFF_FFFF_u32;
+
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+
+    let mut a = 1_u32;
+    let mut b = 0_u32;
+
+    for byte in data {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn crc32_matches_known_values() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32(b"IEND"), 0xAE42_6082);
+    }
+
+    #[test]
+    fn adler32_matches_known_values() {
+        assert_eq!(adler32(b""), 1);
+        assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+    }
+
+    // Reads the stored blocks back and checks the checksum.
+    fn inflate_stored(stream: &[u8]) -> Vec<u8> {
+
+        assert_eq!(&stream[..2], &[0x78, 0x01]);
+        assert_eq!(((stream[0] as u16) << 8 | stream[1] as u16) % 31, 0);
+
+        let mut data = Vec::new();
+        let mut pos = 2;
+        loop {
+            let last = stream[pos] & 1 == 1;
+            assert_eq!(stream[pos] >> 1, 0, "only stored blocks");
+            let len = u16::from_le_bytes([stream[pos + 1], stream[pos + 2]]);
+            let not_len = u16::from_le_bytes([stream[pos + 3], stream[pos + 4]]);
+            assert_eq!(len, !not_len);
+            pos += 5;
+            data.extend_from_slice(&stream[pos..pos + len as usize]);
+            pos += len as usize;
+            if last {
+                break;
+            }
+        }
+
+        let checksum = u32::from_be_bytes([stream[pos], stream[pos + 1], stream[pos + 2], stream[pos + 3]]);
+        assert_eq!(checksum, adler32(&data));
+        assert_eq!(pos + 4, stream.len());
+
+        data
+    }
+
+    #[test]
+    fn zlib_stored_round_trips_over_several_blocks() {
+        let data: Vec<u8> = (0..MAX_STORED_BLOCK_LEN * 2 + 10).map(|i| (i * 31) as u8).collect();
+        assert_eq!(inflate_stored(&zlib_stored(&data)), data);
+        assert_eq!(inflate_stored(&zlib_stored(b"short")), b"short");
+    }
+
+    #[test]
+    fn chunks_have_their_crc() {
+        let pixel = ColorU8 { r: 1, g: 2, b: 3, a: 4 };
+        let png = encode_png(&[pixel.clone(), pixel], 2, 1);
+
+        assert_eq!(&png[..8], &SIGNATURE);
+        // The header chunk: length, type, data and crc.
+        assert_eq!(&png[8..12], &13_u32.to_be_bytes());
+        assert_eq!(&png[12..16], b"IHDR");
+        let crc = u32::from_be_bytes([png[29], png[30], png[31], png[32]]);
+        assert_eq!(crc, crc32(&png[12..29]));
+        assert_eq!(&png[png.len() - 12..], &[0, 0, 0, 0, b'I', b'E', b'N', b'D', 0xAE, 0x42, 0x60, 0x82]);
+    }
+}
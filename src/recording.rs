@@ -0,0 +1,249 @@
+use std::{fs, path::Path};
+
+use crate::{
+    common::ColorU8,
+    compositing::unpremultiply,
+    gif::GifEncoder,
+    png::encode_png,
+    visualizer::Visualizer,
+};
+
+// How long the finished line stays before a step by step animation loops.
+const LAST_STEP_HOLD: f32 = 1.5_f32;
+// Most viewers play shorter GIF delays slower.
+const MIN_GIF_DELAY: u32 = 2;
+// Of canvas pixels kept, about 140 different frames at 800x600.
+const MAX_RECORDING_BYTES: usize = 256 * 1024 * 1024;
+
+#[derive(PartialEq, Clone, Copy)]
+pub enum RecordingFormat {
+    Gif,
+    PngSequence,
+}
+
+impl RecordingFormat {
+
+    pub const ALL: [RecordingFormat; 2] = [RecordingFormat::Gif, RecordingFormat::PngSequence];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            RecordingFormat::Gif => "Animated GIF",
+            RecordingFormat::PngSequence => "PNG sequence",
+        }
+    }
+}
+
+// Canvas pixels, so the bottom row comes first. `duration` is in seconds.
+pub struct Frame {
+    pub pixels: Vec<ColorU8>,
+    pub duration: f32,
+}
+
+pub struct Recorder {
+    pub format: RecordingFormat,
+    pub path: String,
+    pub scale: u16,
+    pub status: String,
+    recording: bool,
+    canvas_size: (u16, u16),
+    frames: Vec<Frame>,
+}
+
+impl Default for Recorder {
+    fn default() -> Self {
+        Self {
+            format: RecordingFormat::Gif,
+            path: "recording.gif".to_string(),
+            scale: 4,
+            status: String::new(),
+            recording: false,
+            canvas_size: (0, 0),
+            frames: Vec::new(),
+        }
+    }
+}
+
+impl Recorder {
+
+    pub fn is_recording(&self) -> bool {
+        self.recording
+    }
+
+    pub fn get_frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn start(&mut self, canvas_size: (u16, u16)) {
+        self.recording = true;
+        self.canvas_size = canvas_size;
+        self.frames.clear();
+        self.status.clear();
+    }
+
+    // A frame equal to the previous one only makes that one last longer.
+    pub fn capture(&mut self, pixels: Vec<ColorU8>, canvas_size: (u16, u16), duration: f32) {
+
+        if canvas_size != self.canvas_size {
+            self.stop();
+            return;
+        }
+
+        if let Some(frame) = self.frames.last_mut() {
+            if frame.pixels == pixels {
+                frame.duration += duration;
+                return;
+            }
+        }
+
+        if self.frames.len() >= max_frames(canvas_size) {
+            self.stop();
+            self.status = format!(
+                "{}, the recording reached its limit of {} MB",
+                self.status,
+                MAX_RECORDING_BYTES / (1024 * 1024)
+            );
+            return;
+        }
+
+        self.frames.push(Frame { pixels, duration });
+    }
+
+    pub fn stop(&mut self) {
+
+        self.recording = false;
+
+        let frames = std::mem::take(&mut self.frames);
+        self.status = match self.save(&frames, self.canvas_size) {
+            Ok(message) => message,
+            Err(err) => err,
+        };
+    }
+
+    pub fn save(&self, frames: &[Frame], canvas_size: (u16, u16)) -> Result<String, String> {
+        save_frames(frames, canvas_size, self.format, &self.path, self.scale)
+    }
+}
+
+// One frame per step of the visualizer, played at its speed. Long lines
+// are cut at the recording limit.
+pub fn step_frames(visualizer: &Visualizer, canvas_size: (u16, u16)) -> Vec<Frame> {
+
+    let (width, height) = canvas_size;
+    let step_count = visualizer.get_trace().len().min(max_frames(canvas_size));
+
+    let mut frames: Vec<Frame> = (0..step_count)
+        .map(|step| Frame {
+            pixels: visualizer.render_step_pixels(step, width, height),
+            duration: 1.0 / visualizer.steps_per_second,
+        })
+        .collect();
+
+    if let Some(frame) = frames.last_mut() {
+        frame.duration += LAST_STEP_HOLD;
+    }
+
+    frames
+}
+
+pub fn save_frames(
+    frames: &[Frame],
+    canvas_size: (u16, u16),
+    format: RecordingFormat,
+    path: &str,
+    scale: u16,
+) -> Result<String, String> {
+
+    if frames.is_empty() {
+        return Err("Nothing was recorded".to_string());
+    }
+
+    if path.is_empty() {
+        return Err("No file path was given".to_string());
+    }
+
+    let (width, height) = canvas_size;
+    let image_width = width.checked_mul(scale);
+    let image_height = height.checked_mul(scale);
+    let (image_width, image_height) = match (image_width, image_height) {
+        (Some(image_width), Some(image_height)) => (image_width, image_height),
+        _ => return Err(format!("A scale of {} makes the image too big", scale)),
+    };
+
+    // Frames are scaled one at a time, all of them at once would take
+    // `scale` squared times the memory of the recording.
+    let to_scaled_image = |frame: &Frame| to_image(&frame.pixels, width, height, scale);
+
+    match format {
+
+        RecordingFormat::Gif => {
+
+            // Rounds the time every frame ends at, so the delays do not
+            // drift from the recorded ones.
+            let mut time = 0.0;
+            let mut shown = 0;
+            let delays: Vec<u16> = frames
+                .iter()
+                .map(|frame| {
+                    time += frame.duration;
+                    let end = (time * 100.0).round() as u32;
+                    let delay = end.saturating_sub(shown).max(MIN_GIF_DELAY);
+                    shown += delay;
+                    delay.min(u16::MAX as u32) as u16
+                })
+                .collect();
+
+            // Scaling does not change which colors there are.
+            let palette_frames: Vec<&[ColorU8]> = frames.iter().map(|frame| frame.pixels.as_slice()).collect();
+            let mut encoder = GifEncoder::new(&palette_frames, image_width, image_height);
+            for (frame, delay) in frames.iter().zip(delays) {
+                encoder.add_frame(&to_scaled_image(frame), delay);
+            }
+
+            fs::write(path, encoder.finish())
+                .map_err(|err| format!("Could not write {}: {}", path, err))?;
+
+            Ok(format!("Saved {} frames to {}", frames.len(), path))
+        }
+
+        RecordingFormat::PngSequence => {
+
+            let stem = Path::new(path).with_extension("");
+            let stem = stem.to_string_lossy();
+
+            for (i, frame) in frames.iter().enumerate() {
+                // PNG has straight alpha, canvases are premultiplied.
+                let image: Vec<ColorU8> = to_scaled_image(frame).iter().map(unpremultiply).collect();
+                let frame_path = format!("{}_{:04}.png", stem, i);
+                fs::write(&frame_path, encode_png(&image, image_width, image_height))
+                    .map_err(|err| format!("Could not write {}: {}", frame_path, err))?;
+            }
+
+            Ok(format!("Saved {} frames as {}_0000.png onwards", frames.len(), stem))
+        }
+    }
+}
+
+fn max_frames(canvas_size: (u16, u16)) -> usize {
+    let frame_bytes = canvas_size.0 as usize * canvas_size.1 as usize * std::mem::size_of::<ColorU8>();
+    (MAX_RECORDING_BYTES / frame_bytes.max(1)).max(1)
+}
+
+// Flips the canvas rows so the top one comes first and makes every pixel
+// `scale` pixels wide and tall.
+fn to_image(pixels: &[ColorU8], width: u16, height: u16, scale: u16) -> Vec<ColorU8> {
+
+    let (width, height, scale) = (width as usize, height as usize, scale as usize);
+    let mut image = Vec::with_capacity(width * height * scale * scale);
+
+    for row in pixels.chunks(width).rev().take(height) {
+        for _ in 0..scale {
+            for color in row {
+                for _ in 0..scale {
+                    image.push(color.clone());
+                }
+            }
+        }
+    }
+
+    image
+}
@@ -0,0 +1,145 @@
+use nalgebra_glm as glm;
+use egui_glfw_gl::egui;
+
+use crate::{
+    camera::Camera2D,
+    line::Line,
+};
+
+// In points.
+const RULER_WIDTH: f32 = 40.0_f32;
+const RULER_HEIGHT: f32 = 18.0_f32;
+const MIN_TICK_SPACING: f32 = 70.0_f32;
+
+const RULER_COLOR: egui::Color32 = egui::Color32::from_rgba_premultiplied(20, 20, 20, 220);
+const TICK_COLOR: egui::Color32 = egui::Color32::from_rgb(200, 200, 200);
+const LABEL_COLOR: egui::Color32 = egui::Color32::from_rgb(230, 230, 230);
+
+// The ruler on the top edge shows canvas x and the one on the left edge
+// canvas y. With a rotated camera the ticks are placed where the edge
+// crosses those values.
+pub fn paint_rulers(painter: &egui::Painter, camera: &Camera2D, pixels_per_point: f32) {
+
+    let origin = camera.get_viewport_pos() / pixels_per_point;
+    let size = camera.get_viewport_size() / pixels_per_point;
+    let at = |x: f32, y: f32| egui::pos2(origin.x + x, origin.y + y);
+
+    painter.rect_filled(
+        egui::Rect::from_min_size(at(0.0, 0.0), egui::vec2(size.x, RULER_HEIGHT)),
+        0.0,
+        RULER_COLOR,
+    );
+    painter.rect_filled(
+        egui::Rect::from_min_size(at(0.0, RULER_HEIGHT), egui::vec2(RULER_WIDTH, size.y)),
+        0.0,
+        RULER_COLOR,
+    );
+
+    let canvas_at = |x: f32, y: f32| {
+        camera.screen_to_canvas(&((origin + glm::vec2(x, y)) * pixels_per_point))
+    };
+
+    // Canvas value at the edge start and its change per point.
+    let top_start = canvas_at(0.0, 0.0).x;
+    let top_slope = canvas_at(1.0, 0.0).x - top_start;
+    let left_start = canvas_at(0.0, 0.0).y;
+    let left_slope = canvas_at(0.0, 1.0).y - left_start;
+
+    for (offset, major, value) in ticks(top_start, top_slope, RULER_WIDTH, size.x) {
+        let tick_length = if major { RULER_HEIGHT } else { RULER_HEIGHT * 0.3 };
+        painter.line_segment(
+            [at(offset, RULER_HEIGHT - tick_length), at(offset, RULER_HEIGHT)],
+            (1.0, TICK_COLOR),
+        );
+        if let Some(value) = value {
+            painter.text(
+                at(offset + 2.0, 0.0),
+                egui::Align2::LEFT_TOP,
+                value,
+                egui::TextStyle::Small,
+                LABEL_COLOR,
+            );
+        }
+    }
+
+    for (offset, major, value) in ticks(left_start, left_slope, RULER_HEIGHT, size.y) {
+        let tick_length = if major { RULER_WIDTH } else { RULER_WIDTH * 0.15 };
+        painter.line_segment(
+            [at(RULER_WIDTH - tick_length, offset), at(RULER_WIDTH, offset)],
+            (1.0, TICK_COLOR),
+        );
+        if let Some(value) = value {
+            painter.text(
+                at(1.0, offset + 1.0),
+                egui::Align2::LEFT_TOP,
+                value,
+                egui::TextStyle::Small,
+                LABEL_COLOR,
+            );
+        }
+    }
+}
+
+pub fn paint_endpoint_labels(
+    painter: &egui::Painter,
+    camera: &Camera2D,
+    pixels_per_point: f32,
+    lines: &[Line],
+) {
+
+    for line in lines {
+        for point in [&line.start, &line.end] {
+            let pos = camera.canvas_to_screen(point) / pixels_per_point;
+            painter.text(
+                egui::pos2(pos.x + 4.0, pos.y - 4.0),
+                egui::Align2::LEFT_BOTTOM,
+                format!("({:.1}, {:.1})", point.x, point.y),
+                egui::TextStyle::Small,
+                LABEL_COLOR,
+            );
+        }
+    }
+}
+
+// Returns the offset in points along the edge of every tick between `from`
+// and `to`, whether it is a major tick and the label of the major ones.
+fn ticks(start: f32, slope: f32, from: f32, to: f32) -> Vec<(f32, bool, Option<String>)> {
+
+    if slope.abs() < f32::EPSILON {
+        return Vec::new();
+    }
+
+    let (step, subdivisions) = tick_step(MIN_TICK_SPACING * slope.abs());
+    let minor_step = step / subdivisions as f32;
+    let decimals = (-step.log10()).ceil().max(0.0) as usize;
+
+    let first_value = start + slope * from;
+    let last_value = start + slope * to;
+    let first = (first_value.min(last_value) / minor_step).ceil() as i64;
+    let last = (first_value.max(last_value) / minor_step).floor() as i64;
+
+    (first..=last)
+        .map(|i| {
+            let value = i as f32 * minor_step;
+            let offset = (value - start) / slope;
+            let major = i % subdivisions as i64 == 0;
+            let label = if major { Some(format!("{:.*}", decimals, value)) } else { None };
+            (offset, major, label)
+        })
+        .collect()
+}
+
+// The smallest 1, 2 or 5 times a power of ten not below `min_step`, and
+// how many minor ticks it is divided into.
+fn tick_step(min_step: f32) -> (f32, u32) {
+
+    let magnitude = 10_f32.powf(min_step.log10().floor());
+
+    for (factor, subdivisions) in [(1.0, 5), (2.0, 4), (5.0, 5)] {
+        if magnitude * factor >= min_step {
+            return (magnitude * factor, subdivisions);
+        }
+    }
+
+    (magnitude * 10.0, 5)
+}
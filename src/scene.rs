@@ -0,0 +1,212 @@
+use crate::{
+    line::Line,
+    history::{History, Command},
+    selection::Selection,
+    layers::{Layers, Layer},
+    scene_graph::{SceneGraph, Group},
+};
+
+// Every edit of `lines` goes through `execute` so it can be undone, and so
+// do adding and removing layers and groups, so lines brought back by an
+// undo find theirs. Groups are edited through it too, the other properties
+// of the layers are not part of the history.
+#[derive(Default)]
+pub struct Scene {
+    pub lines: Vec<Line>,
+    pub history: History,
+    pub selection: Selection,
+    pub layers: Layers,
+    pub graph: SceneGraph,
+}
+
+impl Scene {
+
+    // The selection follows its line when lines before it are added,
+    // removed or moved.
+    pub fn execute(&mut self, command: Command) {
+        self.selection.remap(|index| command.map_index(index));
+        self.history.execute(command, &mut self.lines, &mut self.layers, &mut self.graph);
+        self.validate_selection();
+    }
+
+    pub fn undo(&mut self) {
+        if let Some(command) = self.history.undo(&mut self.lines, &mut self.layers, &mut self.graph) {
+            self.selection.remap(|index| command.unmap_index(index));
+        }
+        self.validate_selection();
+    }
+
+    pub fn redo(&mut self) {
+        if let Some(command) = self.history.redo(&mut self.lines, &mut self.layers, &mut self.graph) {
+            self.selection.remap(|index| command.map_index(index));
+        }
+        self.validate_selection();
+    }
+
+    // Leaves exactly `count` commands applied, undoing or redoing as needed.
+    pub fn go_to(&mut self, count: usize) {
+
+        while self.history.get_undo_count() > count {
+            self.undo();
+        }
+
+        while self.history.get_undo_count() < count && self.history.get_redo_commands().next().is_some() {
+            self.redo();
+        }
+    }
+
+    // Also after hiding or locking a layer.
+    pub fn validate_selection(&mut self) {
+        self.selection.validate(&self.lines, &self.layers);
+    }
+
+    // New lines go to the active layer.
+    pub fn add_line(&mut self, mut line: Line) {
+        line.layer = self.layers.get_active();
+        self.execute(Command::add_line(&self.lines, line));
+    }
+
+    pub fn add_lines(&mut self, mut lines: Vec<Line>) {
+
+        for line in &mut lines {
+            line.layer = self.layers.get_active();
+        }
+
+        if !lines.is_empty() {
+            self.execute(Command::add_lines(&self.lines, lines));
+        }
+    }
+
+    // Lines from a file with layers go to the layers with the same names.
+    // The missing layers are added above the others, undone with the lines.
+    pub fn add_lines_to_named_layers(&mut self, named_lines: Vec<(String, Line)>) {
+
+        if named_lines.is_empty() {
+            return;
+        }
+
+        let mut new_layers: Vec<Layer> = Vec::new();
+        let mut lines = Vec::with_capacity(named_lines.len());
+
+        for (name, mut line) in named_lines {
+            let existing = self.layers
+                .find(&name)
+                .or_else(|| new_layers.iter().find(|layer| layer.name == name).map(|layer| layer.id));
+            line.layer = match existing {
+                Some(id) => id,
+                None => {
+                    let mut layer = self.layers.new_layer();
+                    layer.name = name;
+                    new_layers.push(layer);
+                    new_layers.last().unwrap().id
+                }
+            };
+            lines.push(line);
+        }
+
+        let layer_count = self.layers.get_layers().len();
+        let mut commands: Vec<Command> = new_layers
+            .into_iter()
+            .enumerate()
+            .map(|(i, layer)| Command::AddLayer { position: layer_count + i, layer })
+            .collect();
+        commands.push(Command::add_lines(&self.lines, lines));
+
+        if commands.len() == 1 {
+            self.execute(commands.pop().unwrap());
+        } else {
+            self.execute(Command::Batch { commands });
+        }
+    }
+
+    // Added above the others and made active.
+    pub fn add_layer(&mut self) -> u32 {
+
+        let layer = self.layers.new_layer();
+        let id = layer.id;
+
+        self.execute(Command::AddLayer { position: self.layers.get_layers().len(), layer });
+        self.layers.set_active(id);
+
+        id
+    }
+
+    // The lines with the transforms of their groups applied, in the same
+    // order.
+    pub fn world_lines(&self) -> Vec<Line> {
+        self.lines.iter().map(|line| self.graph.to_world(line)).collect()
+    }
+
+    // The lines that are drawn, transformed.
+    pub fn visible_lines(&self) -> Vec<Line> {
+        self.lines
+            .iter()
+            .filter(|line| self.layers.is_visible(line.layer))
+            .map(|line| self.graph.to_world(line))
+            .collect()
+    }
+
+    // Empty layers only, so no line is left without one. The last layer is
+    // kept.
+    pub fn remove_layer(&mut self, id: u32) {
+
+        if self.lines.iter().any(|line| line.layer == id) || self.layers.get_layers().len() == 1 {
+            return;
+        }
+
+        if let Some(position) = self.layers.get_index(id) {
+            let layer = self.layers.get_layers()[position].clone();
+            self.execute(Command::RemoveLayer { position, layer });
+        }
+    }
+
+    // Added after the others and selected.
+    pub fn add_group(&mut self, parent: Option<u32>) -> u32 {
+
+        let group = self.graph.new_group(parent);
+        let id = group.id;
+
+        self.execute(Command::AddGroup { position: self.graph.get_groups().len(), group });
+        self.graph.select(Some(id));
+
+        id
+    }
+
+    // Groups with lines or other groups in them are kept.
+    pub fn remove_group(&mut self, id: u32) {
+
+        if self.graph.has_children(id) || self.lines.iter().any(|line| line.group == Some(id)) {
+            return;
+        }
+
+        if let Some(position) = self.graph.get_index(id) {
+            let group = self.graph.get_groups()[position].clone();
+            self.execute(Command::RemoveGroup { position, group });
+        }
+    }
+
+    // The group was already edited live, `before` is how it was.
+    pub fn record_group_edit(&mut self, before: Group) {
+
+        let after = match self.graph.get_group(before.id) {
+            Some(group) => group.clone(),
+            None => return,
+        };
+
+        if before != after {
+            self.execute(Command::EditGroup { before, after });
+        }
+    }
+
+    pub fn delete_line(&mut self, index: usize) {
+        self.execute(Command::delete_line(&self.lines, index));
+    }
+
+    // Only the lines that can be edited.
+    pub fn clear(&mut self) {
+        let command = Command::clear(&self.lines, &self.layers);
+        if matches!(&command, Command::Clear { lines } if !lines.is_empty()) {
+            self.execute(command);
+        }
+    }
+}
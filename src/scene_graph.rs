@@ -0,0 +1,375 @@
+use nalgebra_glm as glm;
+
+use crate::line::Line;
+
+#[derive(Clone, PartialEq)]
+pub struct Transform {
+    pub translation: glm::Vec2,
+    // In degrees, counterclockwise.
+    pub rotation: f32,
+    pub scale: glm::Vec2,
+    // How much x moves per unit of y, and y per unit of x.
+    pub shear: glm::Vec2,
+    // Scaling, shearing and rotating keep this point of the group in place.
+    pub pivot: glm::Vec2,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self {
+            translation: glm::vec2(0.0, 0.0),
+            rotation: 0.0,
+            scale: glm::vec2(1.0, 1.0),
+            shear: glm::vec2(0.0, 0.0),
+            pivot: glm::vec2(0.0, 0.0),
+        }
+    }
+}
+
+impl Transform {
+
+    // Points are scaled first, then sheared, rotated and translated, all
+    // around the pivot.
+    pub fn matrix(&self) -> glm::Mat3 {
+
+        let id = glm::Mat3::identity();
+
+        glm::translate2d(&id, &(self.translation + self.pivot))
+            * self.linear_matrix()
+            * glm::translate2d(&id, &-self.pivot)
+    }
+
+    pub fn linear_matrix(&self) -> glm::Mat3 {
+
+        let id = glm::Mat3::identity();
+
+        self.rotation_shear_matrix() * glm::scale2d(&id, &self.scale)
+    }
+
+    pub fn rotation_shear_matrix(&self) -> glm::Mat3 {
+
+        let id = glm::Mat3::identity();
+
+        glm::rotate2d(&id, self.rotation.to_radians())
+            * glm::shear2d_x(&id, self.shear.x)
+            * glm::shear2d_y(&id, self.shear.y)
+    }
+
+    // Where the pivot ends up, in the coordinates of the parent.
+    pub fn get_origin(&self) -> glm::Vec2 {
+        self.translation + self.pivot
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct Group {
+    pub id: u32,
+    pub name: String,
+    pub parent: Option<u32>,
+    pub transform: Transform,
+}
+
+// Groups of lines with a transform each, relative to their parent group.
+// Lines keep their coordinates in the space of their group and are
+// transformed before they are rasterized.
+#[derive(Default)]
+pub struct SceneGraph {
+    groups: Vec<Group>,
+    selected: Option<u32>,
+    next_id: u32,
+}
+
+impl SceneGraph {
+
+    pub fn get_groups(&self) -> &[Group] {
+        &self.groups
+    }
+
+    pub fn get_group(&self, id: u32) -> Option<&Group> {
+        self.groups.iter().find(|group| group.id == id)
+    }
+
+    pub fn get_group_mut(&mut self, id: u32) -> Option<&mut Group> {
+        self.groups.iter_mut().find(|group| group.id == id)
+    }
+
+    // The group the transform gizmo works on.
+    pub fn get_selected(&self) -> Option<u32> {
+        self.selected
+    }
+
+    pub fn select(&mut self, id: Option<u32>) {
+        self.selected = id.filter(|id| self.get_group(*id).is_some());
+    }
+
+    pub fn get_index(&self, id: u32) -> Option<usize> {
+        self.groups.iter().position(|group| group.id == id)
+    }
+
+    // A group with a new id, not added yet. Ids are never reused, so a group
+    // brought back by a redo keeps its own.
+    pub fn new_group(&mut self, parent: Option<u32>) -> Group {
+
+        let id = self.next_id;
+        self.next_id += 1;
+
+        Group {
+            id,
+            name: format!("Group {}", id),
+            parent,
+            transform: Transform::default(),
+        }
+    }
+
+    // Adding, removing and editing groups goes through the history, so
+    // lines brought back by an undo find their group as it was.
+    pub fn insert(&mut self, position: usize, group: Group) {
+        let position = position.min(self.groups.len());
+        self.groups.insert(position, group);
+    }
+
+    // Returns where the group was and the group.
+    pub fn remove(&mut self, id: u32) -> Option<(usize, Group)> {
+
+        let position = self.get_index(id)?;
+        let group = self.groups.remove(position);
+
+        if self.selected == Some(id) {
+            self.selected = None;
+        }
+
+        Some((position, group))
+    }
+
+    // Replaces the group with the same id.
+    pub fn set_group(&mut self, group: Group) {
+        if let Some(old_group) = self.get_group_mut(group.id) {
+            *old_group = group;
+        }
+    }
+
+    pub fn has_children(&self, id: u32) -> bool {
+        self.groups.iter().any(|group| group.parent == Some(id))
+    }
+
+    // Whether `ancestor` is `id` or one of its parents.
+    pub fn is_ancestor(&self, ancestor: u32, id: u32) -> bool {
+
+        let mut current = Some(id);
+
+        // The depth bound stops on cycles.
+        for _ in 0..=self.groups.len() {
+            match current {
+                Some(current_id) if current_id == ancestor => return true,
+                Some(current_id) => current = self.get_group(current_id).and_then(|group| group.parent),
+                None => return false,
+            }
+        }
+
+        false
+    }
+
+    // Children of every group come after it, indented by their depth.
+    pub fn get_tree(&self) -> Vec<(usize, u32)> {
+
+        let mut tree = Vec::with_capacity(self.groups.len());
+        self.push_children(None, 0, &mut tree);
+
+        tree
+    }
+
+    fn push_children(&self, parent: Option<u32>, depth: usize, tree: &mut Vec<(usize, u32)>) {
+
+        // Groups whose parent was lost are listed at the root.
+        let is_root = |group: &Group| match group.parent {
+            None => true,
+            Some(parent) => self.get_group(parent).is_none(),
+        };
+
+        for group in &self.groups {
+            let is_child = match parent {
+                None => is_root(group),
+                Some(parent) => group.parent == Some(parent),
+            };
+            if is_child {
+                tree.push((depth, group.id));
+                self.push_children(Some(group.id), depth + 1, tree);
+            }
+        }
+    }
+
+    // From the group's coordinates to the canvas, through all its parents.
+    // Lines outside any group are not transformed.
+    pub fn world_matrix(&self, group: Option<u32>) -> glm::Mat3 {
+
+        let mut matrix = glm::Mat3::identity();
+        let mut current = group;
+
+        for _ in 0..=self.groups.len() {
+            match current.and_then(|id| self.get_group(id)) {
+                Some(group) => {
+                    matrix = group.transform.matrix() * matrix;
+                    current = group.parent;
+                }
+                None => break,
+            }
+        }
+
+        matrix
+    }
+
+    // The space a group's own transform is expressed in.
+    pub fn parent_matrix(&self, id: u32) -> glm::Mat3 {
+        self.world_matrix(self.get_group(id).and_then(|group| group.parent))
+    }
+
+    pub fn to_world(&self, line: &Line) -> Line {
+
+        let matrix = self.world_matrix(line.group);
+
+        let mut line = line.clone();
+        line.start = transform_point(&matrix, &line.start);
+        line.end = transform_point(&matrix, &line.end);
+
+        line
+    }
+
+    // The same line on the canvas, with coordinates in another group.
+    pub fn move_to_group(&self, line: &Line, group: Option<u32>) -> Line {
+
+        let to_group = inverse_or_identity(&self.world_matrix(group));
+
+        let mut moved = self.to_world(line);
+        moved.start = transform_point(&to_group, &moved.start);
+        moved.end = transform_point(&to_group, &moved.end);
+        moved.group = group;
+
+        moved
+    }
+}
+
+pub fn transform_point(matrix: &glm::Mat3, point: &glm::Vec2) -> glm::Vec2 {
+    (matrix * glm::vec3(point.x, point.y, 1.0)).xy()
+}
+
+pub fn transform_vector(matrix: &glm::Mat3, vector: &glm::Vec2) -> glm::Vec2 {
+    (matrix * glm::vec3(vector.x, vector.y, 0.0)).xy()
+}
+
+// Groups scaled down to nothing can not be undone, the identity keeps
+// edits from blowing up.
+pub fn inverse_or_identity(matrix: &glm::Mat3) -> glm::Mat3 {
+    matrix.try_inverse().unwrap_or_else(glm::Mat3::identity)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    const EPSILON: f32 = 1e-3;
+
+    fn assert_close(a: &glm::Vec2, b: &glm::Vec2) {
+        assert!(glm::distance(a, b) < EPSILON, "{:?} != {:?}", a, b);
+    }
+
+    fn transform(rotation: f32, scale: glm::Vec2, shear: glm::Vec2, pivot: glm::Vec2) -> Transform {
+        Transform {
+            translation: glm::vec2(0.0, 0.0),
+            rotation,
+            scale,
+            shear,
+            pivot,
+        }
+    }
+
+    // A parent group turned and moved, with a child scaled and sheared
+    // around its own pivot.
+    fn graph() -> (SceneGraph, u32, u32) {
+
+        let mut graph = SceneGraph::default();
+
+        let mut parent = graph.new_group(None);
+        parent.transform = transform(30.0, glm::vec2(1.0, 1.0), glm::vec2(0.0, 0.0), glm::vec2(-2.0, 1.0));
+        parent.transform.translation = glm::vec2(10.0, -5.0);
+        let parent_id = parent.id;
+        graph.insert(0, parent);
+
+        let mut child = graph.new_group(Some(parent_id));
+        child.transform = transform(-45.0, glm::vec2(2.0, 0.5), glm::vec2(0.3, -0.2), glm::vec2(4.0, 3.0));
+        child.transform.translation = glm::vec2(-1.0, 2.0);
+        let child_id = child.id;
+        graph.insert(1, child);
+
+        (graph, parent_id, child_id)
+    }
+
+    #[test]
+    fn pivot_stays_in_place() {
+
+        let pivot = glm::vec2(3.0, -4.0);
+        let none = glm::vec2(0.0, 0.0);
+
+        for transform in [
+            transform(0.0, glm::vec2(2.0, 3.0), none, pivot),
+            transform(75.0, glm::vec2(1.0, 1.0), none, pivot),
+            transform(0.0, glm::vec2(1.0, 1.0), glm::vec2(0.5, -0.25), pivot),
+            transform(-120.0, glm::vec2(0.5, 4.0), glm::vec2(1.5, 0.75), pivot),
+        ] {
+            assert_close(&transform_point(&transform.matrix(), &pivot), &pivot);
+
+            // Only the translation moves it.
+            let mut moved = transform.clone();
+            moved.translation = glm::vec2(7.0, 1.0);
+            assert_close(&transform_point(&moved.matrix(), &pivot), &(pivot + moved.translation));
+            assert_close(&moved.get_origin(), &(pivot + moved.translation));
+        }
+    }
+
+    #[test]
+    fn scale_comes_before_rotation() {
+        let transform = transform(90.0, glm::vec2(2.0, 1.0), glm::vec2(0.0, 0.0), glm::vec2(0.0, 0.0));
+        // Scaled along x to (2, 0), then turned to (0, 2).
+        assert_close(&transform_point(&transform.matrix(), &glm::vec2(1.0, 0.0)), &glm::vec2(0.0, 2.0));
+    }
+
+    #[test]
+    fn world_matrix_is_parent_times_child() {
+
+        let (graph, parent, child) = graph();
+        let parent_matrix = graph.get_group(parent).unwrap().transform.matrix();
+        let child_matrix = graph.get_group(child).unwrap().transform.matrix();
+
+        let point = glm::vec2(1.5, -2.5);
+        let expected = transform_point(&parent_matrix, &transform_point(&child_matrix, &point));
+
+        assert_close(&transform_point(&graph.world_matrix(Some(child)), &point), &expected);
+        assert_close(
+            &transform_point(&(parent_matrix * child_matrix), &point),
+            &transform_point(&graph.world_matrix(Some(child)), &point),
+        );
+        assert_close(
+            &transform_point(&graph.parent_matrix(child), &point),
+            &transform_point(&parent_matrix, &point),
+        );
+        assert_close(&transform_point(&graph.world_matrix(None), &point), &point);
+    }
+
+    #[test]
+    fn moving_to_another_group_keeps_the_line_in_place() {
+
+        let (graph, parent, child) = graph();
+
+        let mut line = Line::new(glm::vec2(1.0, 2.0), glm::vec2(-3.0, 0.5));
+        line.group = Some(child);
+        let world = graph.to_world(&line);
+
+        for group in [None, Some(parent), Some(child)] {
+            let moved = graph.move_to_group(&line, group);
+            assert_eq!(moved.group, group);
+            let moved_world = graph.to_world(&moved);
+            assert_close(&moved_world.start, &world.start);
+            assert_close(&moved_world.end, &world.end);
+        }
+    }
+}
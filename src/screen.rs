@@ -4,19 +4,38 @@ use nalgebra_glm as glm;
 use crate::{
     framebuffer::Framebuffer,
     quad::Quad,
-    shader_program::ShaderProgram, common::Color
+    shader_program::ShaderProgram, common::Color,
+    camera::Camera2D,
 };
 
-const ZOOM_DELTA :f32 = 0.1_f32;
-const MAX_SCALE: f32 = 20.0_f32;
-const MIN_SCALE: f32 = 0.01_f32;
 const VERTEX_SHADER_SRC: &str = "src/shaders/screen_vertex.glsl";
 const FRAGMENT_SHADER_SRC: &str = "src/shaders/screen_fragment.glsl";
 
+// The pixel overlays fade in from this zoom on.
+const OVERLAY_MIN_ZOOM: f32 = 6.0_f32;
+
+pub struct ScreenOverlay {
+    pub pixel_grid: bool,
+    pub pixel_centers: bool,
+    pub ideal_lines: bool,
+    pub rulers: bool,
+    pub endpoint_labels: bool,
+}
+
+impl Default for ScreenOverlay {
+    fn default() -> Self {
+        Self {
+            pixel_grid: true,
+            pixel_centers: false,
+            ideal_lines: false,
+            rulers: true,
+            endpoint_labels: false,
+        }
+    }
+}
+
 
 pub struct Screen {
-    scale: f32,
-    pos: glm::Vec2,
     quad: Quad,
     back_color: Color,
     framebuffer: Framebuffer,
@@ -32,8 +51,6 @@ impl Screen {
             Some((VERTEX_SHADER_SRC, FRAGMENT_SHADER_SRC)), None
         );
         Self {
-            scale: 1.0,
-            pos: glm::vec2(0.0, 0.0),
             quad: Quad::default(),
             back_color: Color {r: 0.1, g: 0.1, b: 0.1, a: 1.0},
             framebuffer: Framebuffer::new(width, height),
@@ -41,44 +58,46 @@ impl Screen {
         }
     }
 
-    pub fn update_zoom(&mut self, scroll_amount: f64) {
-
-        self.scale += scroll_amount as f32 * ZOOM_DELTA;
-
-        if self.scale > MAX_SCALE {
-            self.scale = MAX_SCALE;
-        }
-        else if self.scale < MIN_SCALE {
-            self.scale = MIN_SCALE
-        }
-
-    }
-
-    pub fn move_canvas(&mut self, pos_delta: &glm::Vec2) {
-        self.pos += pos_delta;
-    }
-
-    pub fn get_scale(&self) -> f32 {
-        self.scale
-    }
-
-    pub fn get_pos(&self) -> glm::Vec2 {
-        self.pos
-    }
-
-    pub fn render_used_texture(&self) {
-
-        self.framebuffer.bind();
+    // Draws to the camera's viewport. Only the pixel overlays and the axes
+    // are drawn here, the rest is left to the GUI painter.
+    // `framebuffer_scale` takes the viewport from window pixels to the
+    // framebuffer ones.
+    pub fn render_used_texture(
+        &self,
+        camera: &Camera2D,
+        canvas_size: &glm::Vec2,
+        overlay: &ScreenOverlay,
+        framebuffer_scale: f32,
+    ) {
+
+        let viewport_pos = (camera.get_viewport_pos() * framebuffer_scale).map(f32::round);
+        let viewport_size = (camera.get_viewport_size() * framebuffer_scale).map(f32::round);
+
+        self.framebuffer.bind_region(
+            viewport_pos.x as i32,
+            viewport_pos.y as i32,
+            viewport_size.x as i32,
+            viewport_size.y as i32,
+        );
 
         self.shader.bind();
 
-        self.shader.set_uniform_mat3("transform", &self.compute_transform());
+        self.shader.set_uniform_mat3("transform", &camera.compute_transform(canvas_size));
+        self.shader.set_uniform_vec2("canvas_size", canvas_size);
+        self.shader.set_uniform_f32("zoom", camera.get_zoom());
+        self.shader.set_uniform_f32("overlay_min_zoom", OVERLAY_MIN_ZOOM);
+        self.shader.set_uniform_bool("pixel_grid", overlay.pixel_grid);
+        self.shader.set_uniform_bool("pixel_centers", overlay.pixel_centers);
 
         self.quad.render(&self.shader);
 
         self.framebuffer.unbind();
     }
 
+    pub fn set_size(&mut self, width: u16, height: u16) {
+        self.framebuffer.resize(width, height);
+    }
+
     pub fn clear(&self) {
         self.framebuffer.clear(&self.back_color);
     }
@@ -87,15 +106,6 @@ impl Screen {
     pub fn set_background_color(&mut self, color: Color) {
         self.back_color = color;
     }
-
-    pub fn compute_transform(&self) -> glm::Mat3 {
-
-        let translation = glm::translate2d(&glm::Mat3::identity(), &self.pos);
-
-        let scaling = glm::diagonal3x3(&glm::vec3(self.scale, self.scale, 1.0));
-
-        scaling * translation
-    }
 }
 
 impl Default for Screen {
@@ -106,8 +116,6 @@ impl Default for Screen {
         );
 
         Self {
-            scale: 1.0,
-            pos: glm::vec2(0.0, 0.0),
             quad: Quad::default(),
             back_color: Color {r: 0.2, g: 0.2, b: 0.2, a: 1.0},
             framebuffer: Framebuffer::default(),
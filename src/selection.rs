@@ -0,0 +1,170 @@
+use nalgebra_glm as glm;
+
+use crate::{
+    line::Line,
+    history::Command,
+    layers::Layers,
+};
+
+// In screen pixels, divided by the zoom before hit-testing.
+pub const HIT_DISTANCE: f32 = 6.0_f32;
+
+#[derive(PartialEq, Clone, Copy)]
+pub enum Tool {
+    Draw,
+    Select,
+    Measure,
+    Transform,
+}
+
+#[derive(Clone, Copy)]
+enum DragTarget {
+    Start,
+    End,
+    Whole,
+}
+
+// `grab_pos` is on the canvas and `original` in the coordinates of its
+// group.
+struct Drag {
+    target: DragTarget,
+    grab_pos: glm::Vec2,
+    original: Line,
+}
+
+#[derive(Default)]
+pub struct Selection {
+    selected: Option<usize>,
+    drag: Option<Drag>,
+}
+
+impl Selection {
+
+    pub fn get_selected(&self) -> Option<usize> {
+        self.selected
+    }
+
+    pub fn select(&mut self, index: Option<usize>) {
+        self.selected = index;
+        self.drag = None;
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        self.drag.is_some()
+    }
+
+    // `map` tells where the selected line went, None if it is gone.
+    pub fn remap(&mut self, map: impl Fn(usize) -> Option<usize>) {
+        if let Some(index) = self.selected {
+            match map(index) {
+                Some(index) => self.selected = Some(index),
+                None => self.select(None),
+            }
+        }
+    }
+
+    // Drops a selection on a line that is gone, or on a layer that was
+    // hidden or locked.
+    pub fn validate(&mut self, lines: &[Line], layers: &Layers) {
+        let valid = |index: usize| index < lines.len() && layers.is_editable(lines[index].layer);
+        if matches!(self.selected, Some(index) if !valid(index)) {
+            self.select(None);
+        }
+    }
+
+    // Endpoints of the selected line take priority, then the closest line.
+    // Lines are hit where they are drawn, in `world_lines`, and dragged in
+    // the coordinates of their group.
+    pub fn press(
+        &mut self,
+        pos: &glm::Vec2,
+        world_lines: &[Line],
+        lines: &[Line],
+        layers: &Layers,
+        hit_distance: f32,
+    ) {
+
+        if let Some(index) = self.selected {
+
+            let line = &world_lines[index];
+
+            let target = if glm::distance(pos, &line.start) <= hit_distance {
+                Some(DragTarget::Start)
+            } else if glm::distance(pos, &line.end) <= hit_distance {
+                Some(DragTarget::End)
+            } else {
+                None
+            };
+
+            if let Some(target) = target {
+                self.start_drag(target, pos, &lines[index]);
+                return;
+            }
+        }
+
+        let closest = world_lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| layers.is_editable(line.layer))
+            .map(|(i, line)| (i, line.distance_to(pos)))
+            .filter(|(_, distance)| *distance <= hit_distance)
+            .min_by(|(_, a), (_, b)| a.total_cmp(b));
+
+        match closest {
+            Some((index, _)) => {
+                self.selected = Some(index);
+                self.start_drag(DragTarget::Whole, pos, &lines[index]);
+            }
+            None => {
+                self.select(None);
+            }
+        }
+    }
+
+    // `to_local` takes canvas coordinates to the ones of the line's group.
+    pub fn drag(&mut self, pos: &glm::Vec2, lines: &mut [Line], to_local: &glm::Mat3) {
+
+        if let (Some(index), Some(drag)) = (self.selected, &self.drag) {
+
+            let delta = pos - drag.grab_pos;
+            let delta = (to_local * glm::vec3(delta.x, delta.y, 0.0)).xy();
+            let line = &mut lines[index];
+
+            match drag.target {
+                DragTarget::Start => {
+                    line.start = drag.original.start + delta;
+                }
+                DragTarget::End => {
+                    line.end = drag.original.end + delta;
+                }
+                DragTarget::Whole => {
+                    line.start = drag.original.start + delta;
+                    line.end = drag.original.end + delta;
+                }
+            }
+        }
+    }
+
+    // The line was already moved while dragging, the returned command
+    // records the move for the history.
+    pub fn release(&mut self, lines: &[Line]) -> Option<Command> {
+
+        let drag = self.drag.take()?;
+        let index = self.selected?;
+        let after = lines[index].clone();
+
+        if after.start == drag.original.start && after.end == drag.original.end {
+            return None;
+        }
+
+        Some(Command::edit_line(index, drag.original, after))
+    }
+
+    fn start_drag(&mut self, target: DragTarget, pos: &glm::Vec2, line: &Line) {
+        self.drag = Some(Drag {
+            target,
+            grab_pos: *pos,
+            original: line.clone(),
+        });
+    }
+}
@@ -138,6 +138,43 @@ impl ShaderProgram {
         }
     }
 
+    pub fn set_uniform_f32_array(&self, name: &str, floats: &[f32]) {
+
+        self.bind();
+
+        unsafe {
+            gl::Uniform1fv(
+                self.get_uniform_location(name),
+                floats.len() as GLsizei,
+                floats.as_ptr()
+            );
+        }
+    }
+
+    pub fn set_uniform_i32(&self, name: &str, int: i32) {
+
+        self.bind();
+
+        unsafe {
+            gl::Uniform1i(
+                self.get_uniform_location(name),
+                int
+            );
+        }
+    }
+
+    pub fn set_uniform_bool(&self, name: &str, boolean: bool) {
+
+        self.bind();
+
+        unsafe {
+            gl::Uniform1i(
+                self.get_uniform_location(name),
+                boolean as GLint
+            );
+        }
+    }
+
     pub fn set_uniform_mat3(&self, name: &str, mat: &glm::Mat3) {
 
         self.bind();
@@ -179,6 +216,19 @@ impl ShaderProgram {
         }
     }
 
+    pub fn set_uniform_vec4(&self, name: &str, vec: &glm::Vec4) {
+
+        self.bind();
+
+        unsafe {
+            gl::Uniform4fv(
+                self.get_uniform_location(name),
+                1,
+                glm::value_ptr(vec).as_ptr().cast()
+            );
+        }
+    }
+
     fn get_uniform_location(&self, name: &str) -> GLint {
 
         let location: GLint;
@@ -8,6 +8,8 @@ uniform vec2 canvas_size;
 out vec2 frag_pos;
 
 void main() {
-  frag_pos = (pos * canvas_size) / 2;
+  // Fragment centers land on integer canvas coordinates, like the pixels
+  // of the CPU algorithms.
+  frag_pos = (pos * canvas_size) / 2 - 0.5;
   gl_Position = vec4(pos.x, pos.y, 0.0, 1.0);
 }
@@ -1,20 +1,54 @@
 #version 460 core
 
 in vec2 tex_coords;
-in vec2 pos;
 
 uniform sampler2D screen_texture;
+uniform vec2 canvas_size;
+uniform float zoom;
+uniform float overlay_min_zoom;
+uniform bool pixel_grid;
+uniform bool pixel_centers;
 
 out vec4 final_color;
 
+const vec4 AXIS_COLOR = vec4(0.0, 0.0, 1.0, 1.0);
+const vec4 GRID_COLOR = vec4(0.5, 0.5, 0.5, 1.0);
+const vec4 CENTER_COLOR = vec4(1.0, 0.5, 0.0, 1.0);
+// In texels.
+const float CHECKER_SIZE = 8.0;
+
 void main() {
 
-  bool is_axis = abs(pos.x) < 1.0 / 800 || abs(pos.y) < 1.0 / 600;
-  vec4 axis_color = mix(
-    texture(screen_texture, tex_coords),
-    vec4(0.0, 0.0, 1.0, 1.0),
-    0.7
-  );
-  final_color = float(!is_axis) * texture(screen_texture, tex_coords)
-              + float(is_axis) * axis_color;
+  final_color = texture(screen_texture, tex_coords);
+
+  // The canvas is premultiplied, transparent pixels show a checkerboard.
+  vec2 checker_cell = floor(tex_coords * canvas_size / CHECKER_SIZE);
+  float checker = mod(checker_cell.x + checker_cell.y, 2.0) == 0.0 ? 0.35 : 0.55;
+  final_color = vec4(final_color.rgb + checker * (1.0 - final_color.a), 1.0);
+
+  // Fades in between overlay_min_zoom and twice that.
+  float overlay_alpha = smoothstep(overlay_min_zoom, 2.0 * overlay_min_zoom, zoom);
+
+  // In texels, pixel boundaries are at integers. fwidth gives the size of
+  // a screen pixel so the marks keep the same width at any zoom.
+  vec2 texel = tex_coords * canvas_size;
+  vec2 texel_width = fwidth(texel);
+
+  // Canvas coordinates, pixel centers are on integers.
+  vec2 canvas_pos = texel - floor(canvas_size / 2.0) - 0.5;
+  vec2 to_axis = abs(canvas_pos) / texel_width;
+  float is_axis = float(min(to_axis.x, to_axis.y) < 0.5);
+  final_color = mix(final_color, AXIS_COLOR, 0.7 * is_axis);
+
+  if (pixel_grid) {
+    vec2 to_edge = abs(fract(texel + 0.5) - 0.5) / texel_width;
+    float is_edge = float(min(to_edge.x, to_edge.y) < 0.5);
+    final_color = mix(final_color, GRID_COLOR, 0.6 * is_edge * overlay_alpha);
+  }
+
+  if (pixel_centers) {
+    float to_center = length((fract(texel) - 0.5) / texel_width);
+    float is_center = float(to_center < 1.5);
+    final_color = mix(final_color, CENTER_COLOR, is_center * overlay_alpha);
+  }
 }
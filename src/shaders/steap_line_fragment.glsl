@@ -6,14 +6,80 @@ in vec2 frag_pos;
 uniform float m;
 uniform float b;
 uniform vec4 line_color;
+uniform vec4 line_end_color;
+uniform bool gradient_linear;
+uniform bool gradient_by_distance;
+uniform vec2 line_start;
+uniform vec2 line_end;
+// The rounded start of the line and its length along the major axis.
+uniform float major_start;
+uniform float major_length;
+uniform float line_width;
+// Alternating on and off run lengths, starting with an on run.
+uniform float stipple_runs[32];
+uniform int stipple_run_count;
+uniform float stipple_period;
+uniform float stipple_offset;
 
 out vec4 final_color;
 
+bool stipple_on(float major) {
+
+  if (stipple_run_count == 0 || stipple_period <= 0.0) {
+    return true;
+  }
+
+  float pos = mod(abs(round(major) - major_start) + stipple_offset, stipple_period);
+
+  for (int i = 0; i < stipple_run_count; i++) {
+    if (pos < stipple_runs[i]) {
+      return i % 2 == 0;
+    }
+    pos -= stipple_runs[i];
+  }
+
+  return true;
+}
+
+vec3 srgb_to_linear(vec3 color) {
+  return mix(color / 12.92, pow((color + 0.055) / 1.055, vec3(2.4)), greaterThan(color, vec3(0.04045)));
+}
+
+vec3 linear_to_srgb(vec3 color) {
+  return mix(color * 12.92, 1.055 * pow(color, vec3(1.0 / 2.4)) - 0.055, greaterThan(color, vec3(0.0031308)));
+}
+
+vec4 gradient_color() {
+
+  float t = 0.0;
+  if (gradient_by_distance) {
+    vec2 direction = line_end - line_start;
+    float length2 = dot(direction, direction);
+    if (length2 > 0.0) {
+      t = clamp(dot(round(frag_pos) - line_start, direction) / length2, 0.0, 1.0);
+    }
+  }
+  else if (major_length > 0.0) {
+    t = min(abs(round(frag_pos.y) - major_start) / major_length, 1.0);
+  }
+
+  if (gradient_linear) {
+    vec3 color = mix(srgb_to_linear(line_color.rgb), srgb_to_linear(line_end_color.rgb), t);
+    return vec4(linear_to_srgb(color), mix(line_color.a, line_end_color.a, t));
+  }
+
+  return mix(line_color, line_end_color, t);
+}
+
 void main() {
 
 
-  if (int(round(frag_pos.x)) == int(round(m * frag_pos.y + b))) {
-    final_color = vec4(1.0, 0.0, 0.0, 1.0);
+  // Pixels left or right of the one the line passes through, up to the width.
+  int offset = int(round(frag_pos.x)) - int(round(m * frag_pos.y + b));
+  int width = int(line_width);
+
+  if (offset >= -((width - 1) / 2) && offset <= width / 2 && stipple_on(frag_pos.y)) {
+    final_color = gradient_color();
   }
   else {
     discard;
@@ -0,0 +1,187 @@
+use nalgebra_glm as glm;
+
+use crate::line::Line;
+
+// In screen pixels, divided by the zoom like the selection hit distance.
+pub const SNAP_DISTANCE: f32 = 8.0_f32;
+
+#[derive(PartialEq, Clone, Copy)]
+pub enum AngleStep {
+    Deg15,
+    Deg45,
+}
+
+#[derive(PartialEq, Clone, Copy)]
+pub enum SnapKind {
+    None,
+    Pixel,
+    Grid,
+    Endpoint,
+    Midpoint,
+    Intersection,
+    Angle,
+}
+
+pub struct Snap {
+    pub pos: glm::Vec2,
+    pub kind: SnapKind,
+}
+
+pub struct SnapSettings {
+    pub pixel: bool,
+    pub grid: bool,
+    pub grid_size: f32,
+    pub endpoints: bool,
+    pub midpoints: bool,
+    pub intersections: bool,
+    pub angle_step: AngleStep,
+}
+
+impl Default for SnapSettings {
+    fn default() -> Self {
+        Self {
+            pixel: true,
+            grid: false,
+            grid_size: 10.0,
+            endpoints: true,
+            midpoints: false,
+            intersections: false,
+            angle_step: AngleStep::Deg45,
+        }
+    }
+}
+
+impl SnapSettings {
+
+    // With `constrain_from` the result is kept on a ray from that point at
+    // a multiple of the angle step, ignoring the geometric targets.
+    pub fn snap(
+        &self,
+        pos: &glm::Vec2,
+        lines: &[Line],
+        snap_distance: f32,
+        constrain_from: Option<&glm::Vec2>,
+    ) -> Snap {
+
+        if let Some(origin) = constrain_from {
+            return self.snap_to_angle(pos, origin);
+        }
+
+        if let Some(snap) = self.snap_to_geometry(pos, lines, snap_distance) {
+            return snap;
+        }
+
+        self.snap_to_raster(pos)
+    }
+
+    fn snap_to_geometry(
+        &self,
+        pos: &glm::Vec2,
+        lines: &[Line],
+        snap_distance: f32,
+    ) -> Option<Snap> {
+
+        let mut candidates: Vec<Snap> = Vec::new();
+
+        for line in lines {
+
+            if self.endpoints {
+                candidates.push(Snap { pos: line.start, kind: SnapKind::Endpoint });
+                candidates.push(Snap { pos: line.end, kind: SnapKind::Endpoint });
+            }
+
+            if self.midpoints {
+                candidates.push(Snap {
+                    pos: (line.start + line.end) * 0.5,
+                    kind: SnapKind::Midpoint,
+                });
+            }
+        }
+
+        if self.intersections {
+            for (i, a) in lines.iter().enumerate() {
+                for b in &lines[i + 1..] {
+                    if let Some(intersection) = intersect(a, b) {
+                        candidates.push(Snap { pos: intersection, kind: SnapKind::Intersection });
+                    }
+                }
+            }
+        }
+
+        candidates
+            .into_iter()
+            .map(|snap| (glm::distance(pos, &snap.pos), snap))
+            .filter(|(distance, _)| *distance <= snap_distance)
+            .min_by(|(a, _), (b, _)| a.total_cmp(b))
+            .map(|(_, snap)| snap)
+    }
+
+    fn snap_to_raster(&self, pos: &glm::Vec2) -> Snap {
+
+        if self.grid && self.grid_size > 0.0 {
+            Snap {
+                pos: (pos / self.grid_size).map(|c| c.round()) * self.grid_size,
+                kind: SnapKind::Grid,
+            }
+        } else if self.pixel {
+            Snap {
+                pos: pos.map(|c| c.round()),
+                kind: SnapKind::Pixel,
+            }
+        } else {
+            Snap {
+                pos: *pos,
+                kind: SnapKind::None,
+            }
+        }
+    }
+
+    fn snap_to_angle(&self, pos: &glm::Vec2, origin: &glm::Vec2) -> Snap {
+
+        let step = match self.angle_step {
+            AngleStep::Deg15 => 15_f32.to_radians(),
+            AngleStep::Deg45 => 45_f32.to_radians(),
+        };
+
+        let offset = pos - origin;
+        let steps = (offset.y.atan2(offset.x) / step).round() as i32;
+        let angle = steps as f32 * step;
+
+        // Multiples of 45 degrees are kept on whole pixel steps so the
+        // endpoints stay exact, e.g. (k, k) along a diagonal.
+        let snapped = if self.pixel && angle.to_degrees().round() as i32 % 45 == 0 {
+            let direction = glm::vec2(angle.cos().round(), angle.sin().round());
+            let length = glm::dot(&offset, &direction) / glm::dot(&direction, &direction);
+            origin + direction * length.round().max(0.0)
+        } else {
+            let direction = glm::vec2(angle.cos(), angle.sin());
+            origin + direction * glm::dot(&offset, &direction).max(0.0)
+        };
+
+        Snap {
+            pos: snapped,
+            kind: SnapKind::Angle,
+        }
+    }
+}
+
+pub fn intersect(a: &Line, b: &Line) -> Option<glm::Vec2> {
+
+    let r = a.end - a.start;
+    let s = b.end - b.start;
+    let denominator = glm::cross2d(&r, &s);
+
+    if denominator.abs() < f32::EPSILON {
+        return None;
+    }
+
+    let offset = b.start - a.start;
+    let t = glm::cross2d(&offset, &s) / denominator;
+    let u = glm::cross2d(&offset, &r) / denominator;
+
+    if (0.0..=1.0).contains(&t) && (0.0..=1.0).contains(&u) {
+        Some(a.start + r * t)
+    } else {
+        None
+    }
+}
@@ -0,0 +1,111 @@
+use nalgebra_glm as glm;
+
+use crate::{
+    camera::Camera2D,
+    lines_renderer::LineAlgorithem,
+};
+
+#[derive(PartialEq, Clone, Copy)]
+pub enum SplitMode {
+    Single,
+    Two,
+    Four,
+}
+
+impl SplitMode {
+
+    pub const ALL: [SplitMode; 3] = [SplitMode::Single, SplitMode::Two, SplitMode::Four];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SplitMode::Single => "Single",
+            SplitMode::Two => "Two views",
+            SplitMode::Four => "Four views",
+        }
+    }
+
+    pub fn pane_count(&self) -> usize {
+        match self {
+            SplitMode::Single => 1,
+            SplitMode::Two => 2,
+            SplitMode::Four => 4,
+        }
+    }
+}
+
+// The panes share the pan, zoom and rotation of one camera. The first pane
+// shows the selected algorithm, the others their own.
+pub struct SplitView {
+    pub mode: SplitMode,
+    pub algorithems: [LineAlgorithem; 3],
+    active_pane: usize,
+}
+
+impl Default for SplitView {
+    fn default() -> Self {
+        Self {
+            mode: SplitMode::Single,
+            algorithems: [
+                LineAlgorithem::Bresenham,
+                LineAlgorithem::DDA,
+                LineAlgorithem::SlopeInterceptFS,
+            ],
+            active_pane: 0,
+        }
+    }
+}
+
+impl SplitView {
+
+    // `camera` covers the whole window, the returned ones a pane each, in
+    // reading order.
+    pub fn pane_cameras(&self, camera: &Camera2D) -> Vec<Camera2D> {
+
+        let window_size = camera.get_viewport_size();
+
+        let (columns, rows) = match self.mode {
+            SplitMode::Single => (1, 1),
+            SplitMode::Two => (2, 1),
+            SplitMode::Four => (2, 2),
+        };
+
+        let pane_size = glm::vec2(
+            (window_size.x / columns as f32).floor(),
+            (window_size.y / rows as f32).floor(),
+        );
+
+        (0..rows)
+            .flat_map(|row| (0..columns).map(move |column| (row, column)))
+            .map(|(row, column)| {
+                let pane_pos = glm::vec2(column as f32, row as f32).component_mul(&pane_size);
+                camera.with_viewport(&pane_pos, &pane_size)
+            })
+            .collect()
+    }
+
+    pub fn get_pane_algorithem(&self, pane: usize, selected: LineAlgorithem) -> LineAlgorithem {
+        match pane {
+            0 => selected,
+            _ => self.algorithems[pane - 1],
+        }
+    }
+
+    pub fn get_active_pane(&self) -> usize {
+        self.active_pane.min(self.mode.pane_count() - 1)
+    }
+
+    // Input goes to the pane under the cursor. It should not change in the
+    // middle of a drag.
+    pub fn update_active_pane(&mut self, camera: &Camera2D, cursor_pos: &glm::Vec2) {
+
+        let pane = self.pane_cameras(camera).iter().position(|pane_camera| {
+            let min = pane_camera.get_viewport_pos();
+            let max = min + pane_camera.get_viewport_size();
+            (min.x..max.x).contains(&cursor_pos.x) && (min.y..max.y).contains(&cursor_pos.y)
+        });
+
+        if let Some(pane) = pane {
+            self.active_pane = pane;
+        }
+    }
+}
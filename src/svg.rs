@@ -0,0 +1,963 @@
+use std::fs;
+
+use nalgebra_glm as glm;
+use xml::reader::{EventReader, XmlEvent};
+use xml::attribute::OwnedAttribute;
+
+use crate::{
+    line::Line,
+    curves::{flatten_cubic, arc_segment_count},
+};
+
+pub const DEFAULT_TOLERANCE: f32 = 0.25_f32;
+
+const SKIPPED_ELEMENTS: [&str; 7] = [
+    "defs", "clipPath", "mask", "symbol", "marker", "pattern", "style",
+];
+
+pub fn import_svg(
+    path: &str,
+    tolerance: f32,
+    canvas_size: (u16, u16),
+) -> Result<Vec<Line>, String> {
+
+    let src = fs::read_to_string(path)
+        .map_err(|err| format!("Could not read {}: {}", path, err))?;
+
+    parse_svg(&src, tolerance, canvas_size)
+}
+
+pub fn parse_svg(
+    src: &str,
+    tolerance: f32,
+    canvas_size: (u16, u16),
+) -> Result<Vec<Line>, String> {
+
+    let mut importer = SvgImporter::new(tolerance, canvas_size);
+    let mut skip_depth = 0_u32;
+
+    for event in EventReader::from_str(src) {
+
+        match event.map_err(|err| format!("Invalid SVG: {}", err))? {
+
+            XmlEvent::StartElement { name, attributes, .. } => {
+
+                let tag = name.local_name.as_str();
+
+                if skip_depth > 0
+                    || SKIPPED_ELEMENTS.contains(&tag)
+                    || get_attribute(&attributes, "display") == Some("none")
+                {
+                    skip_depth += 1;
+                    continue;
+                }
+
+                importer.start_element(tag, &attributes)?;
+            }
+
+            XmlEvent::EndElement { .. } => {
+                if skip_depth > 0 {
+                    skip_depth -= 1;
+                } else {
+                    importer.end_element();
+                }
+            }
+
+            _ => {}
+        }
+    }
+
+    Ok(importer.lines)
+}
+
+struct SvgImporter {
+    tolerance: f32,
+    canvas_size: (u16, u16),
+    transforms: Vec<glm::Mat3>,
+    lines: Vec<Line>,
+}
+
+impl SvgImporter {
+
+    fn new(tolerance: f32, canvas_size: (u16, u16)) -> Self {
+        Self {
+            tolerance: tolerance.max(0.001),
+            canvas_size,
+            transforms: Vec::new(),
+            lines: Vec::new(),
+        }
+    }
+
+    fn start_element(
+        &mut self,
+        tag: &str,
+        attributes: &[OwnedAttribute]
+    ) -> Result<(), String> {
+
+        let parent = self.current_transform();
+
+        let mut transform = if tag == "svg" {
+            parent * self.viewport_transform(attributes)
+        } else {
+            parent
+        };
+
+        if let Some(src) = get_attribute(attributes, "transform") {
+            transform *= parse_transform(src)?;
+        }
+
+        self.transforms.push(transform);
+
+        let number = |name: &str| {
+            get_attribute(attributes, name)
+                .and_then(parse_length)
+                .unwrap_or(0.0)
+        };
+
+        match tag {
+
+            "line" => {
+                self.add_polyline(&[
+                    glm::vec2(number("x1"), number("y1")),
+                    glm::vec2(number("x2"), number("y2")),
+                ]);
+            }
+
+            "polyline" | "polygon" => {
+                let mut points: Vec<glm::Vec2> = parse_numbers(
+                    get_attribute(attributes, "points").unwrap_or("")
+                )
+                .chunks_exact(2)
+                .map(|xy| glm::vec2(xy[0], xy[1]))
+                .collect();
+
+                if tag == "polygon" && !points.is_empty() {
+                    points.push(points[0]);
+                }
+
+                self.add_polyline(&points);
+            }
+
+            "rect" => {
+                let (x, y) = (number("x"), number("y"));
+                let (w, h) = (number("width"), number("height"));
+
+                if w > 0.0 && h > 0.0 {
+                    self.add_polyline(&[
+                        glm::vec2(x, y),
+                        glm::vec2(x + w, y),
+                        glm::vec2(x + w, y + h),
+                        glm::vec2(x, y + h),
+                        glm::vec2(x, y),
+                    ]);
+                }
+            }
+
+            "circle" => {
+                let r = number("r");
+                self.add_ellipse(glm::vec2(number("cx"), number("cy")), r, r);
+            }
+
+            "ellipse" => {
+                self.add_ellipse(
+                    glm::vec2(number("cx"), number("cy")),
+                    number("rx"),
+                    number("ry"),
+                );
+            }
+
+            "path" => {
+                let subpaths = self.flatten_path(
+                    get_attribute(attributes, "d").unwrap_or("")
+                )?;
+
+                for points in subpaths {
+                    self.add_polyline(&points);
+                }
+            }
+
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn end_element(&mut self) {
+        self.transforms.pop();
+    }
+
+    fn current_transform(&self) -> glm::Mat3 {
+        match self.transforms.last() {
+            Some(transform) => *transform,
+            None => glm::Mat3::identity(),
+        }
+    }
+
+    // Maps the viewBox of an <svg> element onto its width/height, centered
+    // like the default preserveAspectRatio="xMidYMid meet".
+    fn viewport_transform(&self, attributes: &[OwnedAttribute]) -> glm::Mat3 {
+
+        let view_box = parse_numbers(get_attribute(attributes, "viewBox").unwrap_or(""));
+
+        if view_box.len() != 4 || view_box[2] <= 0.0 || view_box[3] <= 0.0 {
+            return glm::Mat3::identity();
+        }
+
+        let width = get_attribute(attributes, "width")
+            .filter(|w| !w.ends_with('%'))
+            .and_then(parse_length)
+            .unwrap_or(self.canvas_size.0 as f32);
+        let height = get_attribute(attributes, "height")
+            .filter(|h| !h.ends_with('%'))
+            .and_then(parse_length)
+            .unwrap_or(self.canvas_size.1 as f32);
+
+        let mut sx = width / view_box[2];
+        let mut sy = height / view_box[3];
+        let mut offset = glm::vec2(0.0, 0.0);
+
+        let preserve_aspect_ratio = get_attribute(attributes, "preserveAspectRatio")
+            .unwrap_or("");
+
+        if !preserve_aspect_ratio.starts_with("none") {
+            let s = sx.min(sy);
+            offset = glm::vec2(
+                (width - view_box[2] * s) / 2.0,
+                (height - view_box[3] * s) / 2.0
+            );
+            sx = s;
+            sy = s;
+        }
+
+        let translation = glm::translate2d(&glm::Mat3::identity(), &offset);
+        let scaling = glm::scale2d(&glm::Mat3::identity(), &glm::vec2(sx, sy));
+        let origin = glm::translate2d(
+            &glm::Mat3::identity(), &glm::vec2(-view_box[0], -view_box[1])
+        );
+
+        translation * scaling * origin
+    }
+
+    // The tolerance is given in canvas pixels, curves are flattened in the
+    // element's user space so it is scaled back by the current transform.
+    fn user_tolerance(&self) -> f32 {
+        let t = self.current_transform();
+        let scale = (t[(0, 0)] * t[(1, 1)] - t[(0, 1)] * t[(1, 0)]).abs().sqrt();
+
+        if scale > f32::EPSILON {
+            self.tolerance / scale
+        } else {
+            self.tolerance
+        }
+    }
+
+    fn to_canvas(&self, point: &glm::Vec2) -> glm::Vec2 {
+        let p = self.current_transform() * glm::vec3(point.x, point.y, 1.0);
+        glm::vec2(
+            p.x - self.canvas_size.0 as f32 / 2.0,
+            self.canvas_size.1 as f32 / 2.0 - p.y,
+        )
+    }
+
+    fn add_polyline(&mut self, points: &[glm::Vec2]) {
+
+        for segment in points.windows(2) {
+
+            let start = self.to_canvas(&segment[0]);
+            let end = self.to_canvas(&segment[1]);
+
+            if start != end {
+                self.lines.push(Line::new(start, end));
+            }
+        }
+    }
+
+    fn add_ellipse(&mut self, center: glm::Vec2, rx: f32, ry: f32) {
+
+        if rx <= 0.0 || ry <= 0.0 {
+            return;
+        }
+
+        let start = center + glm::vec2(rx, 0.0);
+        let mut points = vec![start];
+
+        points.extend(arc_points(
+            start, rx, ry, 0.0, true, true, center - glm::vec2(rx, 0.0),
+            self.user_tolerance(),
+        ));
+        points.extend(arc_points(
+            center - glm::vec2(rx, 0.0), rx, ry, 0.0, true, true, start,
+            self.user_tolerance(),
+        ));
+
+        self.add_polyline(&points);
+    }
+
+    fn flatten_path(&self, d: &str) -> Result<Vec<Vec<glm::Vec2>>, String> {
+
+        let tolerance = self.user_tolerance();
+        let mut lexer = PathLexer::new(d);
+        let mut subpaths: Vec<Vec<glm::Vec2>> = Vec::new();
+        let mut points: Vec<glm::Vec2> = Vec::new();
+
+        let mut current = glm::vec2(0.0, 0.0);
+        let mut subpath_start = current;
+        let mut last_control: Option<glm::Vec2> = None;
+        let mut last_command = b' ';
+
+        let error = |command: u8| format!("Malformed path data for command '{}'", command as char);
+
+        while let Some(command) = lexer.next_command(last_command) {
+
+            let relative = command.is_ascii_lowercase();
+            let origin = if relative { current } else { glm::vec2(0.0, 0.0) };
+            let point = |lexer: &mut PathLexer| -> Result<glm::Vec2, String> {
+                let x = lexer.number().ok_or_else(|| error(command))?;
+                let y = lexer.number().ok_or_else(|| error(command))?;
+                Ok(origin + glm::vec2(x, y))
+            };
+
+            let mut control = None;
+
+            match command.to_ascii_uppercase() {
+
+                b'M' => {
+                    if points.len() > 1 {
+                        subpaths.push(points);
+                    }
+                    current = point(&mut lexer)?;
+                    subpath_start = current;
+                    points = vec![current];
+                }
+
+                b'L' => {
+                    current = point(&mut lexer)?;
+                    points.push(current);
+                }
+
+                b'H' => {
+                    let x = lexer.number().ok_or_else(|| error(command))?;
+                    current.x = if relative { current.x + x } else { x };
+                    points.push(current);
+                }
+
+                b'V' => {
+                    let y = lexer.number().ok_or_else(|| error(command))?;
+                    current.y = if relative { current.y + y } else { y };
+                    points.push(current);
+                }
+
+                b'C' | b'S' => {
+                    let c1 = if command.eq_ignore_ascii_case(&b'C') {
+                        point(&mut lexer)?
+                    } else {
+                        reflect_control(&last_control, &current, last_command, b'C', b'S')
+                    };
+                    let c2 = point(&mut lexer)?;
+                    let end = point(&mut lexer)?;
+
+                    flatten_cubic(current, c1, c2, end, tolerance, &mut points);
+
+                    control = Some(c2);
+                    current = end;
+                }
+
+                b'Q' | b'T' => {
+                    let c = if command.eq_ignore_ascii_case(&b'Q') {
+                        point(&mut lexer)?
+                    } else {
+                        reflect_control(&last_control, &current, last_command, b'Q', b'T')
+                    };
+                    let end = point(&mut lexer)?;
+
+                    let c1 = current + (c - current) * (2.0 / 3.0);
+                    let c2 = end + (c - end) * (2.0 / 3.0);
+                    flatten_cubic(current, c1, c2, end, tolerance, &mut points);
+
+                    control = Some(c);
+                    current = end;
+                }
+
+                b'A' => {
+                    let rx = lexer.number().ok_or_else(|| error(command))?;
+                    let ry = lexer.number().ok_or_else(|| error(command))?;
+                    let rotation = lexer.number().ok_or_else(|| error(command))?;
+                    let large_arc = lexer.flag().ok_or_else(|| error(command))?;
+                    let sweep = lexer.flag().ok_or_else(|| error(command))?;
+                    let end = point(&mut lexer)?;
+
+                    points.extend(arc_points(
+                        current, rx, ry, rotation, large_arc, sweep, end, tolerance
+                    ));
+                    current = end;
+                }
+
+                b'Z' => {
+                    if points.len() > 1 {
+                        points.push(subpath_start);
+                        subpaths.push(points);
+                    }
+                    current = subpath_start;
+                    points = vec![current];
+                }
+
+                _ => {
+                    return Err(format!("Unsupported path command '{}'", command as char));
+                }
+            }
+
+            last_control = control;
+            last_command = command;
+        }
+
+        if points.len() > 1 {
+            subpaths.push(points);
+        }
+
+        Ok(subpaths)
+    }
+}
+
+fn get_attribute<'a>(attributes: &'a [OwnedAttribute], name: &str) -> Option<&'a str> {
+    attributes
+        .iter()
+        .find(|attribute| attribute.name.local_name == name)
+        .map(|attribute| attribute.value.as_str())
+}
+
+fn reflect_control(
+    last_control: &Option<glm::Vec2>,
+    current: &glm::Vec2,
+    last_command: u8,
+    curve: u8,
+    smooth_curve: u8,
+) -> glm::Vec2 {
+
+    let last = last_command.to_ascii_uppercase();
+
+    match last_control {
+        Some(control) if last == curve || last == smooth_curve => {
+            current * 2.0 - control
+        }
+        _ => *current,
+    }
+}
+
+// Endpoint to center parameterization conversion from the SVG
+// implementation notes (F.6.5). Returns the points after `start`.
+#[allow(clippy::too_many_arguments)]
+fn arc_points(
+    start: glm::Vec2,
+    rx: f32,
+    ry: f32,
+    rotation: f32,
+    large_arc: bool,
+    sweep: bool,
+    end: glm::Vec2,
+    tolerance: f32,
+) -> Vec<glm::Vec2> {
+
+    if start == end {
+        return Vec::new();
+    }
+
+    let mut rx = rx.abs();
+    let mut ry = ry.abs();
+
+    if rx < f32::EPSILON || ry < f32::EPSILON {
+        return vec![end];
+    }
+
+    let (sin_phi, cos_phi) = rotation.to_radians().sin_cos();
+    let half = (start - end) * 0.5;
+    let x1 = cos_phi * half.x + sin_phi * half.y;
+    let y1 = -sin_phi * half.x + cos_phi * half.y;
+
+    let lambda = (x1 * x1) / (rx * rx) + (y1 * y1) / (ry * ry);
+    if lambda > 1.0 {
+        rx *= lambda.sqrt();
+        ry *= lambda.sqrt();
+    }
+
+    let num = rx * rx * ry * ry - rx * rx * y1 * y1 - ry * ry * x1 * x1;
+    let den = rx * rx * y1 * y1 + ry * ry * x1 * x1;
+    let mut coef = (num / den).max(0.0).sqrt();
+    if large_arc == sweep {
+        coef = -coef;
+    }
+
+    let cx1 = coef * rx * y1 / ry;
+    let cy1 = -coef * ry * x1 / rx;
+    let mid = (start + end) * 0.5;
+    let center = glm::vec2(
+        cos_phi * cx1 - sin_phi * cy1 + mid.x,
+        sin_phi * cx1 + cos_phi * cy1 + mid.y,
+    );
+
+    let u = glm::vec2((x1 - cx1) / rx, (y1 - cy1) / ry);
+    let v = glm::vec2((-x1 - cx1) / rx, (-y1 - cy1) / ry);
+
+    let theta = u.y.atan2(u.x);
+    let mut delta = glm::cross2d(&u, &v).atan2(glm::dot(&u, &v));
+
+    if !sweep && delta > 0.0 {
+        delta -= 2.0 * std::f32::consts::PI;
+    } else if sweep && delta < 0.0 {
+        delta += 2.0 * std::f32::consts::PI;
+    }
+
+    let segments = arc_segment_count(rx.max(ry), delta, tolerance);
+
+    let mut points: Vec<glm::Vec2> = (1..segments)
+        .map(|i| {
+            let (sin_t, cos_t) = (theta + delta * i as f32 / segments as f32).sin_cos();
+            glm::vec2(
+                center.x + rx * cos_t * cos_phi - ry * sin_t * sin_phi,
+                center.y + rx * cos_t * sin_phi + ry * sin_t * cos_phi,
+            )
+        })
+        .collect();
+
+    points.push(end);
+    points
+}
+
+fn parse_transform(src: &str) -> Result<glm::Mat3, String> {
+
+    let mut transform = glm::Mat3::identity();
+    let mut rest = src.trim();
+
+    while !rest.is_empty() {
+
+        let open = rest.find('(').ok_or_else(|| format!("Invalid transform: {}", src))?;
+        let close = rest.find(')').ok_or_else(|| format!("Invalid transform: {}", src))?;
+
+        let name = rest[..open].trim_matches(|c: char| c.is_whitespace() || c == ',');
+        let args = parse_numbers(&rest[open + 1..close]);
+        let arg = |i: usize| args.get(i).copied().unwrap_or(0.0);
+
+        let id = glm::Mat3::identity();
+
+        transform *= match (name, args.len()) {
+
+            ("matrix", 6) => glm::Mat3::new(
+                arg(0), arg(2), arg(4),
+                arg(1), arg(3), arg(5),
+                0.0, 0.0, 1.0,
+            ),
+
+            ("translate", 1 | 2) => glm::translate2d(&id, &glm::vec2(arg(0), arg(1))),
+
+            ("scale", 1) => glm::scale2d(&id, &glm::vec2(arg(0), arg(0))),
+
+            ("scale", 2) => glm::scale2d(&id, &glm::vec2(arg(0), arg(1))),
+
+            ("rotate", 1) => glm::rotate2d(&id, arg(0).to_radians()),
+
+            ("rotate", 3) => {
+                let pivot = glm::vec2(arg(1), arg(2));
+                glm::translate2d(&id, &pivot)
+                    * glm::rotate2d(&id, arg(0).to_radians())
+                    * glm::translate2d(&id, &-pivot)
+            }
+
+            ("skewX", 1) => glm::shear2d_x(&id, arg(0).to_radians().tan()),
+
+            ("skewY", 1) => glm::shear2d_y(&id, arg(0).to_radians().tan()),
+
+            _ => return Err(format!("Unsupported transform: {}", &rest[..=close])),
+        };
+
+        rest = rest[close + 1..].trim_start();
+    }
+
+    Ok(transform)
+}
+
+fn parse_length(src: &str) -> Option<f32> {
+    PathLexer::new(src).number()
+}
+
+fn parse_numbers(src: &str) -> Vec<f32> {
+
+    let mut lexer = PathLexer::new(src);
+    let mut numbers = Vec::new();
+
+    while let Some(number) = lexer.number() {
+        numbers.push(number);
+    }
+
+    numbers
+}
+
+struct PathLexer<'a> {
+    src: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> PathLexer<'a> {
+
+    fn new(src: &'a str) -> Self {
+        Self {
+            src: src.as_bytes(),
+            pos: 0,
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.src.get(self.pos).copied()
+    }
+
+    fn skip_separators(&mut self) {
+        while matches!(self.peek(), Some(c) if c.is_ascii_whitespace() || c == b',') {
+            self.pos += 1;
+        }
+    }
+
+    // Returns the next explicit command letter, or repeats the previous
+    // command when more numbers follow (a moveto repeats as a lineto).
+    fn next_command(&mut self, last_command: u8) -> Option<u8> {
+
+        self.skip_separators();
+
+        match self.peek()? {
+
+            c if c.is_ascii_alphabetic() && c != b'e' && c != b'E' => {
+                self.pos += 1;
+                Some(c)
+            }
+
+            _ => match last_command {
+                b'M' => Some(b'L'),
+                b'm' => Some(b'l'),
+                b' ' | b'Z' | b'z' => None,
+                c => Some(c),
+            },
+        }
+    }
+
+    fn number(&mut self) -> Option<f32> {
+
+        self.skip_separators();
+
+        let start = self.pos;
+
+        if matches!(self.peek(), Some(b'+' | b'-')) {
+            self.pos += 1;
+        }
+
+        let mut seen_dot = false;
+        let mut seen_digit = false;
+
+        while let Some(c) = self.peek() {
+            if c.is_ascii_digit() {
+                seen_digit = true;
+            } else if c == b'.' && !seen_dot {
+                seen_dot = true;
+            } else {
+                break;
+            }
+            self.pos += 1;
+        }
+
+        if seen_digit && matches!(self.peek(), Some(b'e' | b'E')) {
+            let mantissa_end = self.pos;
+            self.pos += 1;
+            if matches!(self.peek(), Some(b'+' | b'-')) {
+                self.pos += 1;
+            }
+            if matches!(self.peek(), Some(c) if c.is_ascii_digit()) {
+                while matches!(self.peek(), Some(c) if c.is_ascii_digit()) {
+                    self.pos += 1;
+                }
+            } else {
+                self.pos = mantissa_end;
+            }
+        }
+
+        if !seen_digit {
+            self.pos = start;
+            return None;
+        }
+
+        std::str::from_utf8(&self.src[start..self.pos]).ok()?.parse().ok()
+    }
+
+    fn flag(&mut self) -> Option<bool> {
+
+        self.skip_separators();
+
+        let flag = match self.peek()? {
+            b'0' => false,
+            b'1' => true,
+            _ => return None,
+        };
+        self.pos += 1;
+
+        Some(flag)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    const EPSILON: f32 = 1e-3;
+    // Curves are flattened this close to the real ones.
+    const TOLERANCE: f32 = 0.01;
+
+    fn assert_close(a: &glm::Vec2, b: &glm::Vec2) {
+        assert!(glm::distance(a, b) < EPSILON, "{:?} != {:?}", a, b);
+    }
+
+    fn assert_points(points: &[glm::Vec2], expected: &[(f32, f32)]) {
+        assert_eq!(points.len(), expected.len(), "{:?}", points);
+        for (point, (x, y)) in points.iter().zip(expected) {
+            assert_close(point, &glm::vec2(*x, *y));
+        }
+    }
+
+    // Subpaths in user space.
+    fn flatten(d: &str) -> Vec<Vec<glm::Vec2>> {
+        SvgImporter::new(TOLERANCE, (0, 0)).flatten_path(d).unwrap()
+    }
+
+    fn flatten_one(d: &str) -> Vec<glm::Vec2> {
+        let mut subpaths = flatten(d);
+        assert_eq!(subpaths.len(), 1);
+        subpaths.remove(0)
+    }
+
+    fn min_max_y(points: &[glm::Vec2]) -> (f32, f32) {
+        points.iter().fold((f32::MAX, f32::MIN), |(min, max), point| (min.min(point.y), max.max(point.y)))
+    }
+
+    fn transform(src: &str, point: (f32, f32)) -> glm::Vec2 {
+        let p = parse_transform(src).unwrap() * glm::vec3(point.0, point.1, 1.0);
+        glm::vec2(p.x, p.y)
+    }
+
+    #[test]
+    fn moveto_and_lineto_absolute_and_relative() {
+        assert_points(&flatten_one("M 10 10 L 20 10 l 0 10"), &[(10.0, 10.0), (20.0, 10.0), (20.0, 20.0)]);
+        // Numbers after a moveto are linetos, relative after a relative one.
+        assert_points(&flatten_one("M0,0 10,0 10,10"), &[(0.0, 0.0), (10.0, 0.0), (10.0, 10.0)]);
+        assert_points(&flatten_one("m 1 1 2 0 0 2"), &[(1.0, 1.0), (3.0, 1.0), (3.0, 3.0)]);
+        assert_points(&flatten_one("M1-1L-2.5.5"), &[(1.0, -1.0), (-2.5, 0.5)]);
+    }
+
+    #[test]
+    fn horizontal_and_vertical_lines() {
+        assert_points(
+            &flatten_one("M 0 0 H 5 V 5 h -2 v -1"),
+            &[(0.0, 0.0), (5.0, 0.0), (5.0, 5.0), (3.0, 5.0), (3.0, 4.0)],
+        );
+    }
+
+    #[test]
+    fn closepath_returns_to_the_subpath_start() {
+        let subpaths = flatten("M 0 0 L 4 0 L 4 4 Z l 1 1 M 10 10 l 1 0 z");
+        assert_eq!(subpaths.len(), 3);
+        assert_points(&subpaths[0], &[(0.0, 0.0), (4.0, 0.0), (4.0, 4.0), (0.0, 0.0)]);
+        // Relative commands after a close start from where it closed.
+        assert_points(&subpaths[1], &[(0.0, 0.0), (1.0, 1.0)]);
+        assert_points(&subpaths[2], &[(10.0, 10.0), (11.0, 10.0), (10.0, 10.0)]);
+    }
+
+    #[test]
+    fn cubic_curves_absolute_and_relative() {
+        for d in ["M 0 0 C 0 10 10 10 10 0", "M 0 0 c 0 10 10 10 10 0"] {
+            let points = flatten_one(d);
+            assert!(points.len() > 3);
+            assert_close(points.last().unwrap(), &glm::vec2(10.0, 0.0));
+            // The middle of the curve.
+            assert!((min_max_y(&points).1 - 7.5).abs() < 0.05);
+        }
+    }
+
+    #[test]
+    fn smooth_cubic_reflects_the_last_control_point() {
+        let points = flatten_one("M 0 0 C 0 10 10 10 10 0 S 20 -10 20 0");
+        assert_close(points.last().unwrap(), &glm::vec2(20.0, 0.0));
+        let (min, max) = min_max_y(&points);
+        assert!((min + 7.5).abs() < 0.05 && (max - 7.5).abs() < 0.05);
+
+        let relative = flatten_one("m 0 0 c 0 10 10 10 10 0 s 10 -10 10 0");
+        assert_close(relative.last().unwrap(), &glm::vec2(20.0, 0.0));
+        assert!((min_max_y(&relative).0 + 7.5).abs() < 0.05);
+
+        // Without a curve before, the first control point is the current one.
+        let points = flatten_one("M 0 0 S 10 10 10 0");
+        let expected = flatten_one("M 0 0 C 0 0 10 10 10 0");
+        assert_eq!(points, expected);
+    }
+
+    #[test]
+    fn quadratic_and_smooth_quadratic_curves() {
+        let points = flatten_one("M 0 0 Q 5 10 10 0 T 20 0");
+        assert_close(points.last().unwrap(), &glm::vec2(20.0, 0.0));
+        let (min, max) = min_max_y(&points);
+        assert!((max - 5.0).abs() < 0.05 && (min + 5.0).abs() < 0.05);
+
+        let relative = flatten_one("m 0 0 q 5 10 10 0 t 10 0");
+        assert_eq!(points.len(), relative.len());
+        for (a, b) in points.iter().zip(&relative) {
+            assert_close(a, b);
+        }
+    }
+
+    #[test]
+    fn arc_sweep_flag_picks_the_side() {
+        // A half circle around (5, 0), the sweep goes towards positive
+        // angles, which is up in SVG's y down space.
+        let positive = flatten_one("M 0 0 A 5 5 0 0 1 10 0");
+        let negative = flatten_one("M 0 0 A 5 5 0 0 0 10 0");
+
+        for points in [&positive, &negative] {
+            assert_close(points.last().unwrap(), &glm::vec2(10.0, 0.0));
+            for point in points.iter() {
+                assert!((glm::distance(point, &glm::vec2(5.0, 0.0)) - 5.0).abs() < EPSILON);
+            }
+        }
+        assert!(min_max_y(&positive).1 <= EPSILON);
+        assert!(min_max_y(&negative).0 >= -EPSILON);
+    }
+
+    #[test]
+    fn arc_large_arc_flag_picks_the_longer_way() {
+        // The center is 8.66 away from the chord.
+        let small = flatten_one("M 0 0 A 10 10 0 0 0 10 0");
+        let large = flatten_one("M 0 0 A 10 10 0 1 0 10 0");
+
+        let (_, small_max) = min_max_y(&small);
+        let (_, large_max) = min_max_y(&large);
+        assert!((small_max - 1.34).abs() < 0.05, "{}", small_max);
+        assert!((large_max - 18.66).abs() < 0.05, "{}", large_max);
+    }
+
+    #[test]
+    fn arc_flags_without_separators_and_relative_arcs() {
+        let spaced = flatten_one("M 0 0 A 5 5 0 0 1 10 0");
+        assert_eq!(flatten_one("M0 0A5 5 0 0110 0"), spaced);
+
+        let relative = flatten_one("M 0 0 a 5 5 0 0 1 10 0");
+        assert_eq!(relative.len(), spaced.len());
+        for (a, b) in relative.iter().zip(&spaced) {
+            assert_close(a, b);
+        }
+    }
+
+    #[test]
+    fn arc_radii_too_small_are_scaled_up() {
+        let points = flatten_one("M 0 0 A 1 1 0 0 1 10 0");
+        for point in &points {
+            assert!((glm::distance(point, &glm::vec2(5.0, 0.0)) - 5.0).abs() < EPSILON);
+        }
+        // Zero radii draw a straight line.
+        assert_points(&flatten_one("M 0 0 A 0 5 0 0 1 10 0"), &[(0.0, 0.0), (10.0, 0.0)]);
+    }
+
+    #[test]
+    fn malformed_and_unsupported_paths_are_errors() {
+        let importer = SvgImporter::new(TOLERANCE, (0, 0));
+        assert!(importer.flatten_path("M 0 0 L 1").is_err());
+        assert!(importer.flatten_path("M 0 0 A 5 5 0 2 1 10 0").is_err());
+        assert!(importer.flatten_path("M 0 0 X 1 1").is_err());
+    }
+
+    #[test]
+    fn transforms() {
+        assert_close(&transform("translate(10, 20)", (1.0, 1.0)), &glm::vec2(11.0, 21.0));
+        assert_close(&transform("translate(10)", (1.0, 1.0)), &glm::vec2(11.0, 1.0));
+        assert_close(&transform("scale(2)", (1.0, 3.0)), &glm::vec2(2.0, 6.0));
+        assert_close(&transform("scale(2 -1)", (1.0, 3.0)), &glm::vec2(2.0, -3.0));
+        assert_close(&transform("rotate(90)", (1.0, 0.0)), &glm::vec2(0.0, 1.0));
+        assert_close(&transform("rotate(90, 5, 5)", (10.0, 5.0)), &glm::vec2(5.0, 10.0));
+        assert_close(&transform("matrix(1 2 3 4 5 6)", (1.0, 1.0)), &glm::vec2(9.0, 12.0));
+        assert_close(&transform("skewX(45)", (0.0, 1.0)), &glm::vec2(1.0, 1.0));
+        assert_close(&transform("skewY(45)", (1.0, 0.0)), &glm::vec2(1.0, 1.0));
+        // The rightmost transform applies first.
+        assert_close(&transform("translate(10) scale(2)", (1.0, 1.0)), &glm::vec2(12.0, 2.0));
+        assert!(parse_transform("perspective(1)").is_err());
+        assert!(parse_transform("rotate(1, 2)").is_err());
+    }
+
+    #[test]
+    fn nested_transforms_apply_to_the_elements() {
+        let lines = parse_svg(
+            r#"<svg xmlns="http://www.w3.org/2000/svg">
+                <g transform="translate(10, 0)">
+                    <g transform="scale(2)">
+                        <line x1="1" y1="1" x2="2" y2="1"/>
+                    </g>
+                    <line x1="0" y1="0" x2="1" y2="0"/>
+                </g>
+            </svg>"#,
+            TOLERANCE,
+            (0, 0),
+        )
+        .unwrap();
+
+        // The canvas y axis points up.
+        assert_eq!(lines.len(), 2);
+        assert_close(&lines[0].start, &glm::vec2(12.0, -2.0));
+        assert_close(&lines[0].end, &glm::vec2(14.0, -2.0));
+        assert_close(&lines[1].start, &glm::vec2(10.0, 0.0));
+    }
+
+    fn view_box_point(svg_attributes: &str, point: (f32, f32)) -> glm::Vec2 {
+        let src = format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" {}><line x1="{}" y1="{}" x2="-100" y2="-100"/></svg>"#,
+            svg_attributes, point.0, point.1,
+        );
+        // Canvas coordinates back to the SVG ones.
+        let start = parse_svg(&src, TOLERANCE, (100, 50)).unwrap()[0].start;
+        glm::vec2(start.x + 50.0, 25.0 - start.y)
+    }
+
+    #[test]
+    fn view_box_maps_onto_the_viewport() {
+        // Meets in the middle, 5 times bigger and 25 to the right.
+        let meet = r#"viewBox="0 0 10 10" width="100" height="50""#;
+        assert_close(&view_box_point(meet, (0.0, 0.0)), &glm::vec2(25.0, 0.0));
+        assert_close(&view_box_point(meet, (10.0, 10.0)), &glm::vec2(75.0, 50.0));
+
+        let none = r#"viewBox="0 0 10 10" width="100" height="50" preserveAspectRatio="none""#;
+        assert_close(&view_box_point(none, (10.0, 10.0)), &glm::vec2(100.0, 50.0));
+
+        let offset = r#"viewBox="5 5 10 10" width="10" height="10""#;
+        assert_close(&view_box_point(offset, (5.0, 5.0)), &glm::vec2(0.0, 0.0));
+
+        // Without a size the viewBox fills the canvas.
+        assert_close(&view_box_point(r#"viewBox="0 0 200 100""#, (200.0, 100.0)), &glm::vec2(100.0, 50.0));
+    }
+
+    #[test]
+    fn shapes_and_skipped_elements() {
+        let lines = parse_svg(
+            r#"<svg xmlns="http://www.w3.org/2000/svg">
+                <rect x="0" y="0" width="4" height="2"/>
+                <polygon points="0,0 4,0 4,4"/>
+                <polyline points="0 0 1 1 2 0"/>
+                <defs><line x1="0" y1="0" x2="1" y2="1"/></defs>
+                <g display="none"><line x1="0" y1="0" x2="1" y2="1"/></g>
+            </svg>"#,
+            TOLERANCE,
+            (0, 0),
+        )
+        .unwrap();
+        assert_eq!(lines.len(), 4 + 3 + 2);
+
+        let circle = parse_svg(
+            r#"<svg xmlns="http://www.w3.org/2000/svg"><circle cx="5" cy="5" r="3"/></svg>"#,
+            TOLERANCE,
+            (0, 0),
+        )
+        .unwrap();
+        assert!(circle.len() > 8);
+        for line in &circle {
+            assert!((glm::distance(&line.start, &glm::vec2(5.0, -5.0)) - 3.0).abs() < EPSILON);
+        }
+    }
+}
@@ -11,6 +11,10 @@ use glfw::{Action, Context, Key, };
 use egui_backend::egui as egui;
 use egui::{vec2, Pos2, Rect};
 
+// A right button press moving less than this, in pixels, counts as a click
+// instead of a pan.
+const CLICK_DRAG_THRESHOLD: f32 = 3.0_f32;
+
 pub struct Gui {
     glfw: glfw::Glfw,
     window: glfw::Window,
@@ -22,6 +26,12 @@ pub struct Gui {
     start_time: Instant,
     scroll_amount: f64,
     cursor_left_presses: VecDeque<glm::Vec2>,
+    cursor_right_clicks: VecDeque<glm::Vec2>,
+    right_press_pos: Option<glm::Vec2>,
+    pan_cursor_pos: Option<glm::Vec2>,
+    pan_delta: glm::Vec2,
+    key_presses: Vec<(Key, glfw::Modifiers)>,
+    resized_framebuffer: Option<(i32, i32)>,
 }
 
 impl Gui {
@@ -37,7 +47,7 @@ impl Gui {
         glfw.window_hint(glfw::WindowHint::ContextVersion(4, 6));
         glfw.window_hint(glfw::WindowHint::OpenGlProfile(glfw::OpenGlProfileHint::Core));
         glfw.window_hint(glfw::WindowHint::DoubleBuffer(true));
-        glfw.window_hint(glfw::WindowHint::Resizable(false));
+        glfw.window_hint(glfw::WindowHint::Resizable(true));
 
         let (mut window, events) = glfw.create_window(
             width, height, title, glfw::WindowMode::Windowed
@@ -49,6 +59,7 @@ impl Gui {
         window.set_mouse_button_polling(true);
 
         window.set_scroll_polling(true);
+        window.set_framebuffer_size_polling(true);
 
         window.make_current();
 
@@ -87,10 +98,16 @@ impl Gui {
             start_time,
             scroll_amount: 0.0,
             cursor_left_presses: VecDeque::new(),
+            cursor_right_clicks: VecDeque::new(),
+            right_press_pos: None,
+            pan_cursor_pos: None,
+            pan_delta: glm::vec2(0.0, 0.0),
+            key_presses: Vec::new(),
+            // The first frame sizes everything to the real framebuffer.
+            resized_framebuffer: Some((width, height)),
         }
     }
 
-    #[allow(dead_code)]
     pub fn get_window_size(&self) -> (i32, i32) {
         self.window.get_size()
     }
@@ -146,6 +163,48 @@ impl Gui {
         egui_backend::egui::Window::new("GUI").show(&self.egui_ctx, gui_fn);
     }
 
+    // `pos` in window pixels, the tooltip goes below and to the right.
+    pub fn show_tooltip(&self, pos: &glm::Vec2, gui_fn: impl FnOnce(&mut egui::Ui)) {
+        let pos = pos / self.get_pixels_per_point();
+        egui::show_tooltip_at(
+            &self.egui_ctx,
+            egui::Id::new("canvas_tooltip"),
+            Some(egui::pos2(pos.x + 16.0, pos.y + 16.0)),
+            gui_fn,
+        );
+    }
+
+    pub fn is_cursor_over_gui(&self) -> bool {
+        self.egui_ctx.is_pointer_over_area()
+    }
+
+    // Paints behind the GUI windows, in points (see get_pixels_per_point).
+    pub fn get_overlay_painter(&self) -> egui::Painter {
+        self.egui_ctx.layer_painter(egui::LayerId::background())
+    }
+
+    // Window pixels, the ones of the cursor and the cameras, per point.
+    pub fn get_pixels_per_point(&self) -> f32 {
+        self.native_pixels_per_point / self.get_framebuffer_scale()
+    }
+
+    // Framebuffer pixels per window pixel. On HiDPI displays that scale
+    // the window, like Retina ones, the framebuffer has more.
+    pub fn get_framebuffer_scale(&self) -> f32 {
+        let (width, _) = self.window.get_size();
+        let (framebuffer_width, _) = self.window.get_framebuffer_size();
+        if width > 0 && framebuffer_width > 0 {
+            framebuffer_width as f32 / width as f32
+        } else {
+            1.0
+        }
+    }
+
+    // The new framebuffer size, once per resize.
+    pub fn consume_resize(&mut self) -> Option<(i32, i32)> {
+        self.resized_framebuffer.take()
+    }
+
     pub fn consume_scroll_amount(&mut self) -> f64 {
         let used_scroll_amount = self.scroll_amount;
         self.scroll_amount = 0.0;
@@ -156,6 +215,42 @@ impl Gui {
         self.cursor_left_presses.pop_front()
     }
 
+    pub fn consume_cursor_right_click_pos(&mut self) -> Option<glm::Vec2> {
+        self.cursor_right_clicks.pop_front()
+    }
+
+    // Cursor movement in pixels while the middle or right button is held.
+    pub fn consume_pan_delta(&mut self) -> glm::Vec2 {
+        let used_pan_delta = self.pan_delta;
+        self.pan_delta = glm::vec2(0.0, 0.0);
+        used_pan_delta
+    }
+
+    // Modifiers must match exactly, ignoring caps and num lock.
+    pub fn consume_key_press(&mut self, key: Key, modifiers: glfw::Modifiers) -> bool {
+
+        let relevant_modifiers = glfw::Modifiers::Shift
+            | glfw::Modifiers::Control
+            | glfw::Modifiers::Alt
+            | glfw::Modifiers::Super;
+
+        let press = self.key_presses.iter().position(|(pressed_key, pressed_modifiers)| {
+            *pressed_key == key && (*pressed_modifiers & relevant_modifiers) == modifiers
+        });
+
+        match press {
+            Some(index) => {
+                self.key_presses.remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn is_left_button_down(&self) -> bool {
+        self.window.get_mouse_button(glfw::MouseButtonLeft) == glfw::Action::Press
+    }
+
     pub fn get_cursor_pos(&self) -> glm::Vec2 {
         let (x, y) = self.window.get_cursor_pos();
         glm::vec2(x as f32, y as f32)
@@ -163,13 +258,21 @@ impl Gui {
 
     fn handle_window_events(&mut self) {
 
+        self.key_presses.clear();
+
         for (_, event) in glfw::flush_messages(&self.events) {
 
             match event {
 
-                glfw::WindowEvent::Key(Key::Escape, _, Action::Press, _) => {
-                    self.window.set_should_close(true);
-                },
+                glfw::WindowEvent::Key(key, _, Action::Press | Action::Repeat, modifiers)
+                    if !self.egui_ctx.wants_keyboard_input() =>
+                {
+                    self.key_presses.push((key, modifiers));
+                }
+
+                glfw::WindowEvent::FramebufferSize(width, height) => {
+                    self.resized_framebuffer = Some((width, height));
+                }
 
                 glfw::WindowEvent::Scroll(_, y) => {
                     if !self.egui_ctx.is_pointer_over_area() {
@@ -188,10 +291,59 @@ impl Gui {
                     }
                 }
 
+                glfw::WindowEvent::MouseButton(
+                    button @ (glfw::MouseButtonMiddle | glfw::MouseButtonRight),
+                    glfw::Action::Press,
+                    _
+                ) if !self.egui_ctx.is_pointer_over_area() => {
+                    let cursor_pos = self.get_cursor_pos();
+                    self.pan_cursor_pos = Some(cursor_pos);
+                    if button == glfw::MouseButtonRight {
+                        self.right_press_pos = Some(cursor_pos);
+                    }
+                }
+
+                glfw::WindowEvent::MouseButton(
+                    button @ (glfw::MouseButtonMiddle | glfw::MouseButtonRight),
+                    glfw::Action::Release,
+                    _
+                ) => {
+                    self.pan_cursor_pos = None;
+                    if button == glfw::MouseButtonRight {
+                        let cursor_pos = self.get_cursor_pos();
+                        match self.right_press_pos.take() {
+                            Some(press_pos)
+                                if glm::distance(&press_pos, &cursor_pos) < CLICK_DRAG_THRESHOLD =>
+                            {
+                                self.cursor_right_clicks.push_back(cursor_pos);
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+
+                glfw::WindowEvent::CursorPos(x, y) => {
+                    if let Some(last_pos) = self.pan_cursor_pos {
+                        let cursor_pos = glm::vec2(x as f32, y as f32);
+                        self.pan_delta += cursor_pos - last_pos;
+                        self.pan_cursor_pos = Some(cursor_pos);
+                    }
+                }
+
                 _ => {}
             }
             egui_backend::handle_event(event, &mut self.egui_input_state);
         }
+
+        // The painter only takes its size on creation.
+        if let Some((width, height)) = self.resized_framebuffer {
+            if width > 0 && height > 0 {
+                self.painter.cleanup();
+                self.painter = egui_backend::Painter::new(
+                    &mut self.window, width as u32, height as u32
+                );
+            }
+        }
     }
 }
 
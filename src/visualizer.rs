@@ -0,0 +1,146 @@
+use std::time::Duration;
+
+use crate::{
+    common::ColorU8,
+    line::Line,
+    lines_renderer::{LinesRenderer, LineAlgorithem, TraceStep},
+};
+
+const DRAWN_COLOR: ColorU8 = ColorU8 { r: 255, g: 255, b: 255, a: 255 };
+const CURRENT_COLOR: ColorU8 = ColorU8 { r: 255, g: 140, b: 0, a: 255 };
+const PENDING_COLOR: ColorU8 = ColorU8 { r: 60, g: 60, b: 60, a: 255 };
+
+// Plays the rasterization of one line a pixel at a time.
+pub struct Visualizer {
+    pub enabled: bool,
+    pub steps_per_second: f32,
+    line: Option<usize>,
+    trace: Vec<TraceStep>,
+    step: usize,
+    playing: bool,
+    elapsed: f32,
+}
+
+impl Default for Visualizer {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            steps_per_second: 4.0,
+            line: None,
+            trace: Vec::new(),
+            step: 0,
+            playing: false,
+            elapsed: 0.0,
+        }
+    }
+}
+
+impl Visualizer {
+
+    pub fn is_active(&self) -> bool {
+        self.enabled && !self.trace.is_empty()
+    }
+
+    pub fn get_trace(&self) -> &[TraceStep] {
+        &self.trace
+    }
+
+    pub fn get_step(&self) -> usize {
+        self.step
+    }
+
+    pub fn set_step(&mut self, step: usize) {
+        self.step = step.min(self.trace.len().saturating_sub(1));
+        self.elapsed = 0.0;
+    }
+
+    pub fn step_forward(&mut self) {
+        self.playing = false;
+        self.set_step(self.step + 1);
+    }
+
+    pub fn step_back(&mut self) {
+        self.playing = false;
+        self.set_step(self.step.saturating_sub(1));
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    // Playing from the last step starts over.
+    pub fn toggle_playing(&mut self) {
+        if !self.playing && self.step + 1 >= self.trace.len() {
+            self.set_step(0);
+        }
+        self.playing = !self.playing;
+    }
+
+    // Traces the selected line again every frame so edits show up right
+    // away. Selecting another line starts over.
+    pub fn update(
+        &mut self,
+        lines: &[Line],
+        selected: Option<usize>,
+        algorithem: &LineAlgorithem,
+        tex_width: u16,
+        tex_height: u16,
+        dt: &Duration,
+    ) {
+
+        if selected != self.line {
+            self.line = selected;
+            self.playing = false;
+            self.set_step(0);
+        }
+
+        self.trace = match selected {
+            Some(index) if self.enabled => {
+                LinesRenderer::trace(algorithem, &lines[index], tex_width, tex_height)
+            }
+            _ => Vec::new(),
+        };
+        self.set_step(self.step);
+
+        if self.playing {
+
+            self.elapsed += dt.as_secs_f32();
+
+            let steps = (self.elapsed * self.steps_per_second) as usize;
+            if steps > 0 {
+                self.elapsed -= steps as f32 / self.steps_per_second;
+                self.step = (self.step + steps).min(self.trace.len().saturating_sub(1));
+            }
+
+            if self.step + 1 >= self.trace.len() {
+                self.playing = false;
+            }
+        }
+    }
+
+    pub fn render_pixels(&self, tex_width: u16, tex_height: u16) -> Vec<ColorU8> {
+        self.render_step_pixels(self.step, tex_width, tex_height)
+    }
+
+    pub fn render_step_pixels(&self, current_step: usize, tex_width: u16, tex_height: u16) -> Vec<ColorU8> {
+
+        let mut pixels = vec![ColorU8::default(); tex_width as usize * tex_height as usize];
+
+        for (i, step) in self.trace.iter().enumerate() {
+
+            if step.pixel.x >= tex_width || step.pixel.y >= tex_height {
+                continue;
+            }
+
+            let color = match i.cmp(&current_step) {
+                std::cmp::Ordering::Less => &DRAWN_COLOR,
+                std::cmp::Ordering::Equal => &CURRENT_COLOR,
+                std::cmp::Ordering::Greater => &PENDING_COLOR,
+            };
+
+            pixels[step.pixel.y as usize * tex_width as usize + step.pixel.x as usize] = color.clone();
+        }
+
+        pixels
+    }
+}